@@ -0,0 +1,174 @@
+use anyhow::{Result, anyhow};
+use chrono::{Duration, Utc};
+
+use crate::llm::credentials::{self, Keystore};
+use crate::llm::{RoutingStats, Secret};
+
+/// Routing log written by `LlmRouter`
+const ROUTING_LOG_PATH: &str = "routing/log.jsonl";
+
+/// Run a CLI subcommand if one was given. Returns `false` when the TUI should start instead.
+pub async fn run(args: &[String]) -> Result<bool> {
+    match args.first().map(String::as_str) {
+        None => Ok(false),
+        Some("stats") => {
+            run_stats(&args[1..]).await?;
+            Ok(true)
+        }
+        Some("key") => {
+            run_key(&args[1..]).await?;
+            Ok(true)
+        }
+        Some("help") | Some("--help") | Some("-h") => {
+            print_usage();
+            Ok(true)
+        }
+        Some(other) => Err(anyhow!("Unknown command '{}'. Run `deskagent help` for usage.", other)),
+    }
+}
+
+fn print_usage() {
+    println!("Usage: deskagent [COMMAND]");
+    println!();
+    println!("Without a command the TUI is started.");
+    println!();
+    println!("Commands:");
+    println!("  stats [--hours N | --all] [--json]   LLM routing statistics (default: last 24 hours)");
+    println!("  key add <name>                       Store an API key (read from stdin) in the encrypted keystore");
+    println!("  key remove <name>                    Delete a keystore entry");
+    println!("  key list                             List keystore entries");
+    println!("  help                                 Show this message");
+    println!();
+    println!("The keystore lives at ~/.deskagent/keystore.json (override with {}).", credentials::KEYSTORE_PATH_ENV);
+    println!("Entries named after a provider are used when its api_key is empty.");
+}
+
+async fn run_stats(args: &[String]) -> Result<()> {
+    let mut hours: Option<i64> = Some(24);
+    let mut json = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--hours" => {
+                let value = iter.next().ok_or_else(|| anyhow!("--hours requires a value"))?;
+                hours = Some(value.parse().map_err(|_| anyhow!("Invalid --hours value: {}", value))?);
+            }
+            "--all" => hours = None,
+            "--json" => json = true,
+            other => return Err(anyhow!("Unknown stats option: {}", other)),
+        }
+    }
+
+    let since = hours.map(|h| Utc::now() - Duration::hours(h));
+    let stats = RoutingStats::from_log(ROUTING_LOG_PATH, since).await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        println!("{}", stats.format_report());
+    }
+
+    Ok(())
+}
+
+async fn run_key(args: &[String]) -> Result<()> {
+    let mut keystore = Keystore::open(Keystore::default_path()).await?;
+
+    match (args.first().map(String::as_str), args.get(1)) {
+        (Some("add"), Some(name)) => {
+            let passphrase = match credentials::passphrase_from_env() {
+                Some(passphrase) => passphrase,
+                None => read_secret("Keystore passphrase: ")?,
+            };
+            let value = read_secret(&format!("API key for '{}': ", name))?;
+            if value.is_empty() {
+                return Err(anyhow!("Empty API key"));
+            }
+            keystore.insert(name, &value, &passphrase)?;
+            keystore.save().await?;
+            println!("Stored '{}' in {}", name, keystore.path().display());
+        }
+        (Some("remove"), Some(name)) => {
+            if !keystore.remove(name) {
+                return Err(anyhow!("No keystore entry named '{}'", name));
+            }
+            keystore.save().await?;
+            println!("Removed '{}'", name);
+        }
+        (Some("list"), None) => {
+            let entries = keystore.list();
+            if entries.is_empty() {
+                println!("No keys in {}", keystore.path().display());
+            }
+            for (name, created_at) in entries {
+                println!("{:<20} added {}", name, created_at.format("%Y-%m-%d %H:%M"));
+            }
+        }
+        _ => return Err(anyhow!("Usage: deskagent key add <name> | key remove <name> | key list")),
+    }
+
+    Ok(())
+}
+
+/// Read one line from stdin, prompting on stderr so piped input works too.
+/// Echo is turned off while typing when stdin is a terminal.
+fn read_secret(prompt: &str) -> Result<Secret> {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    eprint!("{}", prompt);
+    std::io::stderr().flush()?;
+
+    let stdin = std::io::stdin();
+    let echo_guard = if stdin.is_terminal() { EchoGuard::disable()? } else { None };
+    let mut line = String::new();
+    let read = stdin.lock().read_line(&mut line);
+    if echo_guard.is_some() {
+        // The newline the user typed wasn't echoed either
+        eprintln!();
+    }
+    drop(echo_guard);
+    read?;
+    Ok(Secret::new(line.trim_end_matches(['\r', '\n'])))
+}
+
+/// Restores terminal echo on stdin when dropped
+#[cfg(unix)]
+struct EchoGuard {
+    original: nix::sys::termios::Termios,
+}
+
+#[cfg(unix)]
+impl EchoGuard {
+    fn disable() -> Result<Option<Self>> {
+        use nix::sys::termios::{tcgetattr, tcsetattr, LocalFlags, SetArg};
+
+        let original = tcgetattr(std::io::stdin())?;
+        let mut silent = original.clone();
+        silent.local_flags.remove(LocalFlags::ECHO);
+        tcsetattr(std::io::stdin(), SetArg::TCSANOW, &silent)?;
+        Ok(Some(Self { original }))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for EchoGuard {
+    fn drop(&mut self) {
+        use nix::sys::termios::{tcsetattr, SetArg};
+
+        if let Err(e) = tcsetattr(std::io::stdin(), SetArg::TCSANOW, &self.original) {
+            log::warn!("Failed to restore terminal echo: {}", e);
+        }
+    }
+}
+
+/// Echo can't be disabled here, so input stays visible
+#[cfg(not(unix))]
+struct EchoGuard;
+
+#[cfg(not(unix))]
+impl EchoGuard {
+    fn disable() -> Result<Option<Self>> {
+        Ok(None)
+    }
+}
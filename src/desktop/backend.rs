@@ -0,0 +1,470 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::sleep;
+
+use super::cursor::FilePosition;
+use super::tmux::TmuxTerminal;
+
+/// Input sent to the focused application
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Keystroke {
+    Text(String),
+    /// Shortcut with the platform's primary modifier (Cmd on macOS, Ctrl elsewhere)
+    Shortcut(Shortcut),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Shortcut {
+    Save,
+    DocumentEnd,
+}
+
+/// Drives desktop applications: the editor for EDIT and a visible terminal
+#[async_trait::async_trait]
+pub trait DesktopBackend: std::fmt::Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Start the editor `app`, optionally on a file at a line and column
+    async fn open_editor(&self, app: &str, position: Option<&FilePosition>) -> Result<()>;
+
+    async fn focus_app(&self, app: &str) -> Result<()>;
+
+    /// Type into the focused window of `app`
+    async fn send_keys(&self, app: &str, keys: &[Keystroke]) -> Result<()>;
+
+    async fn open_terminal(&self, app: &str) -> Result<()>;
+
+    /// Run `command` in a terminal window the user can watch
+    async fn run_in_terminal(&self, app: &str, command: &str) -> Result<()>;
+
+    /// Give the UI time to catch up after opening or focusing something
+    async fn wait_for_ui(&self, duration: Duration) {
+        sleep(duration).await;
+    }
+}
+
+/// AppleScript on macOS, xdotool/ydotool and a terminal emulator on Linux
+pub fn default_backend() -> Arc<dyn DesktopBackend> {
+    if cfg!(target_os = "macos") {
+        Arc::new(AppleScriptBackend)
+    } else {
+        Arc::new(LinuxBackend::detect())
+    }
+}
+
+/// macOS backend using `osascript` and System Events
+#[derive(Debug, Clone, Default)]
+pub struct AppleScriptBackend;
+
+#[async_trait::async_trait]
+impl DesktopBackend for AppleScriptBackend {
+    fn name(&self) -> &'static str {
+        "applescript"
+    }
+
+    async fn open_editor(&self, _app: &str, position: Option<&FilePosition>) -> Result<()> {
+        open_with_cursor_cli(position)?;
+        sleep(Duration::from_millis(1000)).await;
+        Ok(())
+    }
+
+    async fn focus_app(&self, app: &str) -> Result<()> {
+        osascript(&format!("tell application \"{}\" to activate", app), "Focus")?;
+        sleep(Duration::from_millis(200)).await;
+        Ok(())
+    }
+
+    async fn send_keys(&self, app: &str, keys: &[Keystroke]) -> Result<()> {
+        let strokes: Vec<String> = keys.iter()
+            .map(|key| match key {
+                Keystroke::Text(text) => format!("keystroke \"{}\"", text.replace('"', "\\\"").replace('\n', "\\n")),
+                Keystroke::Shortcut(Shortcut::Save) => "key code 1 using command down".to_string(),
+                Keystroke::Shortcut(Shortcut::DocumentEnd) => "key code 125 using command down".to_string(),
+            })
+            .collect();
+        let script = format!(
+            r#"
+            tell application "{}"
+                activate
+                delay 0.1
+                tell application "System Events"
+                    {}
+                end tell
+            end tell
+            "#,
+            app,
+            strokes.join("\n                    delay 0.1\n                    ")
+        );
+        osascript(&script, "Keystroke")
+    }
+
+    async fn open_terminal(&self, app: &str) -> Result<()> {
+        osascript(&format!("tell application \"{}\"\n activate\n do script \"\"\nend tell", app), "Terminal")?;
+        sleep(Duration::from_millis(500)).await;
+        Ok(())
+    }
+
+    async fn run_in_terminal(&self, app: &str, command: &str) -> Result<()> {
+        let script = format!(
+            "tell application \"{}\"\n activate\n do script \"{}\"\nend tell",
+            app,
+            command.replace('"', "\\\"")
+        );
+        osascript(&script, "Terminal command")
+    }
+}
+
+/// How keystrokes reach windows on Linux
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LinuxInput {
+    /// X11
+    Xdotool,
+    /// Wayland and X11, through the ydotoold daemon
+    Ydotool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LinuxTerminal {
+    GnomeTerminal,
+    Kitty,
+    /// The detached `deskagent` tmux session, for machines without a display
+    Tmux,
+}
+
+/// Linux backend: xdotool or ydotool for keys, wmctrl or xdotool for focus
+#[derive(Debug, Clone)]
+pub struct LinuxBackend {
+    input: Option<LinuxInput>,
+    wmctrl: bool,
+    terminal: LinuxTerminal,
+}
+
+impl LinuxBackend {
+    pub fn new(input: Option<LinuxInput>, terminal: LinuxTerminal) -> Self {
+        Self { input, wmctrl: program_exists("wmctrl"), terminal }
+    }
+
+    /// Pick tools from what is installed, preferring ydotool on Wayland
+    pub fn detect() -> Self {
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+        let input = match (program_exists("xdotool"), program_exists("ydotool")) {
+            (_, true) if wayland => Some(LinuxInput::Ydotool),
+            (true, _) => Some(LinuxInput::Xdotool),
+            (false, true) => Some(LinuxInput::Ydotool),
+            (false, false) => None,
+        };
+        let terminal = if program_exists("gnome-terminal") {
+            LinuxTerminal::GnomeTerminal
+        } else if program_exists("kitty") {
+            LinuxTerminal::Kitty
+        } else {
+            LinuxTerminal::Tmux
+        };
+        Self::new(input, terminal)
+    }
+
+    fn input(&self) -> Result<LinuxInput> {
+        self.input.ok_or_else(|| anyhow!("Sending keystrokes on Linux needs xdotool or ydotool"))
+    }
+
+    fn focus_command(&self, app: &str) -> Result<Vec<String>> {
+        if self.wmctrl {
+            return Ok(args(&["wmctrl", "-a", app]));
+        }
+        match self.input()? {
+            LinuxInput::Xdotool => Ok(args(&["xdotool", "search", "--onlyvisible", "--name", app, "windowactivate", "--sync"])),
+            LinuxInput::Ydotool => Err(anyhow!("Focusing windows with ydotool needs wmctrl")),
+        }
+    }
+
+    fn key_command(&self, key: &Keystroke) -> Result<Vec<String>> {
+        Ok(match (self.input()?, key) {
+            (LinuxInput::Xdotool, Keystroke::Text(text)) => args(&["xdotool", "type", "--delay", "1", "--", text]),
+            (LinuxInput::Xdotool, Keystroke::Shortcut(Shortcut::Save)) => args(&["xdotool", "key", "ctrl+s"]),
+            (LinuxInput::Xdotool, Keystroke::Shortcut(Shortcut::DocumentEnd)) => args(&["xdotool", "key", "ctrl+End"]),
+            (LinuxInput::Ydotool, Keystroke::Text(text)) => args(&["ydotool", "type", "--", text]),
+            // Raw key codes: 29 is left Ctrl, 31 is S, 107 is End
+            (LinuxInput::Ydotool, Keystroke::Shortcut(Shortcut::Save)) => args(&["ydotool", "key", "29:1", "31:1", "31:0", "29:0"]),
+            (LinuxInput::Ydotool, Keystroke::Shortcut(Shortcut::DocumentEnd)) => args(&["ydotool", "key", "29:1", "107:1", "107:0", "29:0"]),
+        })
+    }
+
+    /// Emulator command line; `None` for tmux, which goes through `TmuxTerminal`
+    fn terminal_command(&self, command: Option<&str>) -> Option<Vec<String>> {
+        match (self.terminal, command) {
+            (LinuxTerminal::GnomeTerminal, None) => Some(args(&["gnome-terminal"])),
+            (LinuxTerminal::GnomeTerminal, Some(command)) => {
+                Some(args(&["gnome-terminal", "--", "bash", "-c", &format!("{}; exec bash", command)]))
+            }
+            (LinuxTerminal::Kitty, None) => Some(args(&["kitty", "--detach"])),
+            (LinuxTerminal::Kitty, Some(command)) => Some(args(&["kitty", "--detach", "--hold", "bash", "-c", command])),
+            (LinuxTerminal::Tmux, _) => None,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl DesktopBackend for LinuxBackend {
+    fn name(&self) -> &'static str {
+        "linux"
+    }
+
+    async fn open_editor(&self, _app: &str, position: Option<&FilePosition>) -> Result<()> {
+        open_with_cursor_cli(position)?;
+        sleep(Duration::from_millis(1000)).await;
+        Ok(())
+    }
+
+    async fn focus_app(&self, app: &str) -> Result<()> {
+        run(&self.focus_command(app)?)?;
+        sleep(Duration::from_millis(200)).await;
+        Ok(())
+    }
+
+    async fn send_keys(&self, _app: &str, keys: &[Keystroke]) -> Result<()> {
+        for key in keys {
+            run(&self.key_command(key)?)?;
+            sleep(Duration::from_millis(100)).await;
+        }
+        Ok(())
+    }
+
+    async fn open_terminal(&self, _app: &str) -> Result<()> {
+        let Some(command) = self.terminal_command(None) else {
+            return TmuxTerminal::default().ensure_session();
+        };
+        run(&command)?;
+        sleep(Duration::from_millis(500)).await;
+        Ok(())
+    }
+
+    async fn run_in_terminal(&self, _app: &str, command: &str) -> Result<()> {
+        let Some(terminal_command) = self.terminal_command(Some(command)) else {
+            let tmux = TmuxTerminal::default();
+            let window = tmux.window(tmux.session())?;
+            return tmux.send_keys(&window, command);
+        };
+        run(&terminal_command)
+    }
+}
+
+/// Something a `RecordingBackend` was asked to do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DesktopAction {
+    OpenEditor(String),
+    OpenFile { app: String, path: String, line: Option<u32>, column: Option<u32> },
+    FocusApp(String),
+    SendKeys { app: String, keys: Vec<Keystroke> },
+    OpenTerminal(String),
+    RunInTerminal { app: String, command: String },
+}
+
+/// The file a `RecordingBackend` has open, with the caret as a byte offset
+#[derive(Debug)]
+struct OpenBuffer {
+    path: PathBuf,
+    content: String,
+    caret: usize,
+}
+
+/// Headless backend that records every action and acts as a minimal editor:
+/// typed text goes into the open file at the caret and `Save` writes it to disk.
+/// Terminal commands are recorded, not run.
+#[derive(Debug, Default)]
+pub struct RecordingBackend {
+    actions: Mutex<Vec<DesktopAction>>,
+    buffer: Mutex<Option<OpenBuffer>>,
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn actions(&self) -> Vec<DesktopAction> {
+        self.actions.lock().expect("recording lock poisoned").clone()
+    }
+
+    fn record(&self, action: DesktopAction) {
+        self.actions.lock().expect("recording lock poisoned").push(action);
+    }
+}
+
+#[async_trait::async_trait]
+impl DesktopBackend for RecordingBackend {
+    fn name(&self) -> &'static str {
+        "recording"
+    }
+
+    async fn open_editor(&self, app: &str, position: Option<&FilePosition>) -> Result<()> {
+        let Some(position) = position else {
+            self.record(DesktopAction::OpenEditor(app.to_string()));
+            return Ok(());
+        };
+        self.record(DesktopAction::OpenFile {
+            app: app.to_string(),
+            path: position.file_path.clone(),
+            line: position.line,
+            column: position.column,
+        });
+        let path = PathBuf::from(&position.file_path);
+        let content = std::fs::read_to_string(&path).unwrap_or_default();
+        let caret = caret_at(&content, position.line, position.column);
+        *self.buffer.lock().expect("recording lock poisoned") = Some(OpenBuffer { path, content, caret });
+        Ok(())
+    }
+
+    async fn focus_app(&self, app: &str) -> Result<()> {
+        self.record(DesktopAction::FocusApp(app.to_string()));
+        Ok(())
+    }
+
+    async fn send_keys(&self, app: &str, keys: &[Keystroke]) -> Result<()> {
+        self.record(DesktopAction::SendKeys { app: app.to_string(), keys: keys.to_vec() });
+        let mut buffer = self.buffer.lock().expect("recording lock poisoned");
+        let Some(buffer) = buffer.as_mut() else { return Ok(()) };
+        for key in keys {
+            match key {
+                Keystroke::Text(text) => {
+                    buffer.content.insert_str(buffer.caret, text);
+                    buffer.caret += text.len();
+                }
+                Keystroke::Shortcut(Shortcut::DocumentEnd) => buffer.caret = buffer.content.len(),
+                Keystroke::Shortcut(Shortcut::Save) => write_buffer(&buffer.path, &buffer.content)?,
+            }
+        }
+        Ok(())
+    }
+
+    async fn open_terminal(&self, app: &str) -> Result<()> {
+        self.record(DesktopAction::OpenTerminal(app.to_string()));
+        Ok(())
+    }
+
+    async fn run_in_terminal(&self, app: &str, command: &str) -> Result<()> {
+        self.record(DesktopAction::RunInTerminal { app: app.to_string(), command: command.to_string() });
+        Ok(())
+    }
+
+    async fn wait_for_ui(&self, _duration: Duration) {}
+}
+
+/// Byte offset of a 1-based line and column, clamped to the content
+fn caret_at(content: &str, line: Option<u32>, column: Option<u32>) -> usize {
+    let Some(line) = line else { return 0 };
+    let line_start: usize = content.split_inclusive('\n')
+        .take(line.saturating_sub(1) as usize)
+        .map(str::len)
+        .sum();
+    let line_text = content[line_start..].split('\n').next().unwrap_or("");
+    let column_offset: usize = line_text.chars()
+        .take(column.unwrap_or(1).saturating_sub(1) as usize)
+        .map(char::len_utf8)
+        .sum();
+    line_start + column_offset
+}
+
+fn write_buffer(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// `cursor <file> --goto line:column`; the CLI is the same on macOS and Linux
+fn open_with_cursor_cli(position: Option<&FilePosition>) -> Result<()> {
+    let mut command = vec!["cursor".to_string()];
+    if let Some(position) = position {
+        command.push(position.file_path.clone());
+        match (position.line, position.column) {
+            (Some(line), Some(column)) => command.extend(["--goto".to_string(), format!("{}:{}", line, column)]),
+            (Some(line), None) => command.extend(["--goto".to_string(), line.to_string()]),
+            _ => {}
+        }
+    }
+    run(&command)
+}
+
+fn osascript(script: &str, what: &str) -> Result<()> {
+    let output = Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .output()
+        .map_err(|e| anyhow!("Failed to execute AppleScript: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!("{} AppleScript failed: {}", what, String::from_utf8_lossy(&output.stderr)));
+    }
+    Ok(())
+}
+
+fn run(command: &[String]) -> Result<()> {
+    let output = Command::new(&command[0])
+        .args(&command[1..])
+        .output()
+        .map_err(|e| anyhow!("Failed to run {}: {}", command[0], e))?;
+    if !output.status.success() {
+        return Err(anyhow!("{} failed: {}", command[0], String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+fn args(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|part| part.to_string()).collect()
+}
+
+fn program_exists(name: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(name).is_file()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_linux_command_lines() {
+        let x11 = LinuxBackend { input: Some(LinuxInput::Xdotool), wmctrl: false, terminal: LinuxTerminal::Kitty };
+        assert_eq!(x11.focus_command("Cursor").unwrap()[..3], args(&["xdotool", "search", "--onlyvisible"]));
+        assert_eq!(x11.key_command(&Keystroke::Shortcut(Shortcut::Save)).unwrap(), args(&["xdotool", "key", "ctrl+s"]));
+        assert_eq!(x11.key_command(&Keystroke::Text("-v".to_string())).unwrap(), args(&["xdotool", "type", "--delay", "1", "--", "-v"]));
+        assert_eq!(x11.terminal_command(Some("cargo test")), Some(args(&["kitty", "--detach", "--hold", "bash", "-c", "cargo test"])));
+
+        let wayland = LinuxBackend { input: Some(LinuxInput::Ydotool), wmctrl: true, terminal: LinuxTerminal::Tmux };
+        assert_eq!(wayland.focus_command("Cursor").unwrap(), args(&["wmctrl", "-a", "Cursor"]));
+        assert_eq!(wayland.key_command(&Keystroke::Shortcut(Shortcut::DocumentEnd)).unwrap()[2..], args(&["29:1", "107:1", "107:0", "29:0"]));
+        assert_eq!(wayland.terminal_command(Some("ls")), None);
+
+        let bare = LinuxBackend { input: None, wmctrl: false, terminal: LinuxTerminal::GnomeTerminal };
+        assert!(bare.key_command(&Keystroke::Text("x".to_string())).unwrap_err().to_string().contains("xdotool or ydotool"));
+    }
+
+    #[tokio::test]
+    async fn test_recording_backend_edits_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("notes.md");
+        std::fs::write(&path, "first\nsecond\n").unwrap();
+        let file_path = path.to_string_lossy().to_string();
+
+        let backend = RecordingBackend::new();
+        let position = FilePosition { file_path: file_path.clone(), line: Some(2), column: Some(1) };
+        backend.open_editor("Cursor", Some(&position)).await.unwrap();
+        backend.send_keys("Cursor", &[Keystroke::Text("inserted ".to_string())]).await.unwrap();
+        backend.send_keys("Cursor", &[
+            Keystroke::Shortcut(Shortcut::DocumentEnd),
+            Keystroke::Text("last\n".to_string()),
+            Keystroke::Shortcut(Shortcut::Save),
+        ]).await.unwrap();
+        backend.run_in_terminal("Terminal", "cargo test").await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\ninserted second\nlast\n");
+        let actions = backend.actions();
+        assert_eq!(actions.len(), 4);
+        assert_eq!(actions[3], DesktopAction::RunInTerminal { app: "Terminal".to_string(), command: "cargo test".to_string() });
+    }
+}
@@ -1,7 +1,20 @@
 // Desktop control module - placeholder
+pub mod audit;
+pub mod backend;
 pub mod cursor;
+pub mod output;
+pub mod policy;
+pub mod sandbox;
+pub mod shell;
 pub mod terminal;
+pub mod tmux;
 
 // Re-exports for convenience
+pub use audit::{CommandAuditEntry, CommandAuditLog};
+pub use backend::{DesktopBackend, LinuxBackend, RecordingBackend};
 pub use cursor::CursorController;
-pub use terminal::TerminalController;
\ No newline at end of file
+pub use output::{CargoMessages, Diagnostics, DiffNumstat, GitStatus, LibtestReport, ParsedOutput};
+pub use policy::{CommandPolicy, CommandPolicyConfig, PolicyContext, PolicyDecision};
+pub use sandbox::{Sandbox, SandboxConfig};
+pub use terminal::{TerminalConfig, TerminalController, TerminalError};
+pub use tmux::{TmuxConfig, TmuxTerminal};
\ No newline at end of file
@@ -0,0 +1,673 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::shell;
+use super::terminal::CommandResult;
+
+/// A typed view of a command's output, obtained with `CommandResult::parsed`
+pub trait ParsedOutput: Sized {
+    /// Name the parser is registered under in `OUTPUT_PARSERS`
+    const NAME: &'static str;
+
+    fn parse(result: &CommandResult) -> Result<Self>;
+}
+
+/// Commands a parser understands, matched like the policy's idempotent command table
+struct ParserRegistration {
+    parser: &'static str,
+    /// Anchored regex for the program name
+    program: &'static str,
+    /// Regex the arguments, joined by spaces, must match
+    args: Option<&'static str>,
+}
+
+const OUTPUT_PARSERS: &[ParserRegistration] = &[
+    ParserRegistration {
+        parser: CargoMessages::NAME,
+        program: "cargo",
+        args: Some(r"^(?:\+\S+\s)?(?:build|check|clippy|test|bench|run|doc|rustc)\b.*--message-format[= ]json"),
+    },
+    ParserRegistration { parser: LibtestReport::NAME, program: "cargo", args: Some(r"^(?:\+\S+\s)?(?:test|bench)\b") },
+    ParserRegistration { parser: GitStatus::NAME, program: "git", args: Some(r"^(?:-C\s\S+\s)?status\b.*--porcelain=v2") },
+    ParserRegistration { parser: DiffNumstat::NAME, program: "git", args: Some(r"^(?:-C\s\S+\s)?(?:diff|show|log)\b.*--numstat") },
+    ParserRegistration { parser: Diagnostics::NAME, program: ".*", args: None },
+];
+
+/// Parsers registered for any simple command in `command_line`, in registration order
+pub fn parsers_for(command_line: &str) -> Vec<&'static str> {
+    let Ok(script) = shell::parse(command_line) else { return Vec::new() };
+    let mut commands = Vec::new();
+    script.visit(&mut |command, _| commands.push(command));
+
+    OUTPUT_PARSERS.iter()
+        .filter(|registration| {
+            let program = Regex::new(&format!("^(?:{})$", registration.program)).expect("built-in parser registrations are valid");
+            let args = registration.args.map(|args| Regex::new(args).expect("built-in parser registrations are valid"));
+            commands.iter().any(|command| {
+                let Some(name) = command.program() else { return false };
+                let matches_args = match &args {
+                    Some(args) => args.is_match(&command.args().join(" ")),
+                    None => true,
+                };
+                program.is_match(name) && matches_args
+            })
+        })
+        .map(|registration| registration.parser)
+        .collect()
+}
+
+/// Parse `result` as `T`, refusing commands `T` is not registered for
+pub fn parse<T: ParsedOutput>(result: &CommandResult) -> Result<T> {
+    if !parsers_for(&result.command).contains(&T::NAME) {
+        return Err(anyhow!("No {} parser is registered for '{}'", T::NAME, result.command));
+    }
+    T::parse(result)
+}
+
+/// `cargo ... --message-format=json` messages; other stdout lines, such as test output, are skipped
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CargoMessages {
+    pub messages: Vec<CargoMessage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum CargoMessage {
+    CompilerMessage { package_id: String, target: CargoTarget, message: CompilerDiagnostic },
+    CompilerArtifact { package_id: String, target: CargoTarget, #[serde(default)] fresh: bool },
+    BuildScriptExecuted { package_id: String },
+    BuildFinished { success: bool },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CargoTarget {
+    pub name: String,
+    #[serde(default)]
+    pub kind: Vec<String>,
+}
+
+/// A rustc (or clippy) diagnostic with its spans and notes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompilerDiagnostic {
+    pub message: String,
+    /// `error`, `warning`, `note`, `help`, ...
+    pub level: String,
+    pub code: Option<DiagnosticCode>,
+    #[serde(default)]
+    pub spans: Vec<DiagnosticSpan>,
+    #[serde(default)]
+    pub children: Vec<CompilerDiagnostic>,
+    pub rendered: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticCode {
+    /// Error code or lint name, e.g. `E0308` or `clippy::needless_return`
+    pub code: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticSpan {
+    pub file_name: String,
+    pub line_start: u32,
+    pub line_end: u32,
+    pub column_start: u32,
+    pub column_end: u32,
+    pub is_primary: bool,
+    pub label: Option<String>,
+}
+
+impl CompilerDiagnostic {
+    pub fn primary_span(&self) -> Option<&DiagnosticSpan> {
+        self.spans.iter().find(|span| span.is_primary).or(self.spans.first())
+    }
+}
+
+impl CargoMessages {
+    /// Compiler diagnostics, excluding the per-crate "N warnings emitted" summaries
+    pub fn diagnostics(&self) -> impl Iterator<Item = &CompilerDiagnostic> {
+        self.messages.iter()
+            .filter_map(|message| match message {
+                CargoMessage::CompilerMessage { message, .. } => Some(message),
+                _ => None,
+            })
+            .filter(|diagnostic| !diagnostic.spans.is_empty() || diagnostic.code.is_some() || diagnostic.level == "error")
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &CompilerDiagnostic> {
+        self.diagnostics().filter(|diagnostic| diagnostic.level.starts_with("error"))
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &CompilerDiagnostic> {
+        self.diagnostics().filter(|diagnostic| diagnostic.level == "warning")
+    }
+
+    /// Result of the `build-finished` message; `None` when cargo stopped before sending it
+    pub fn build_succeeded(&self) -> Option<bool> {
+        self.messages.iter().rev().find_map(|message| match message {
+            CargoMessage::BuildFinished { success } => Some(*success),
+            _ => None,
+        })
+    }
+}
+
+impl ParsedOutput for CargoMessages {
+    const NAME: &'static str = "cargo-messages";
+
+    fn parse(result: &CommandResult) -> Result<Self> {
+        let mut messages = Vec::new();
+        for line in result.stdout.lines() {
+            let Some(value) = json_line(line) else { continue };
+            if value.get("reason").is_none() {
+                continue;
+            }
+            messages.push(serde_json::from_value(value).map_err(|e| anyhow!("Invalid cargo message: {}", e))?);
+        }
+        Ok(Self { messages })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStatus {
+    Ok,
+    Failed,
+    Ignored,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestOutcome {
+    pub name: String,
+    pub status: TestStatus,
+    /// Seconds, when reported (`--report-time` or JSON output)
+    pub exec_time: Option<f64>,
+    /// Captured output of a failed test, JSON format only
+    pub stdout: Option<String>,
+}
+
+/// Totals reported at the end of one test binary
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SuiteSummary {
+    pub passed: u32,
+    pub failed: u32,
+    pub ignored: u32,
+    pub measured: u32,
+    pub filtered_out: u32,
+}
+
+/// Test results from libtest, in its JSON format (`-Z unstable-options --format json`)
+/// or the default human format
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LibtestReport {
+    pub tests: Vec<TestOutcome>,
+    pub suites: Vec<SuiteSummary>,
+}
+
+/// The fields of libtest JSON events used here
+#[derive(Debug, Deserialize)]
+struct LibtestEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    event: String,
+    name: Option<String>,
+    exec_time: Option<f64>,
+    stdout: Option<String>,
+    #[serde(default)]
+    passed: u32,
+    #[serde(default)]
+    failed: u32,
+    #[serde(default)]
+    ignored: u32,
+    #[serde(default)]
+    measured: u32,
+    #[serde(default)]
+    filtered_out: u32,
+}
+
+impl LibtestReport {
+    pub fn passed(&self) -> u32 {
+        self.total(|suite| suite.passed, TestStatus::Ok)
+    }
+
+    pub fn failed(&self) -> u32 {
+        self.total(|suite| suite.failed, TestStatus::Failed)
+    }
+
+    pub fn ignored(&self) -> u32 {
+        self.total(|suite| suite.ignored, TestStatus::Ignored)
+    }
+
+    pub fn failing_tests(&self) -> Vec<&str> {
+        self.tests.iter()
+            .filter(|test| test.status == TestStatus::Failed)
+            .map(|test| test.name.as_str())
+            .collect()
+    }
+
+    /// Suite totals when any suite finished, otherwise the individual results seen so far
+    fn total(&self, count: impl Fn(&SuiteSummary) -> u32, status: TestStatus) -> u32 {
+        if self.suites.is_empty() {
+            self.tests.iter().filter(|test| test.status == status).count() as u32
+        } else {
+            self.suites.iter().map(count).sum()
+        }
+    }
+
+    fn push_event(&mut self, event: LibtestEvent) {
+        let status = match event.event.as_str() {
+            "ok" => TestStatus::Ok,
+            "failed" | "timeout" => TestStatus::Failed,
+            "ignored" => TestStatus::Ignored,
+            _ => return,
+        };
+        match (event.kind.as_str(), event.name) {
+            ("suite", _) => self.suites.push(SuiteSummary {
+                passed: event.passed,
+                failed: event.failed,
+                ignored: event.ignored,
+                measured: event.measured,
+                filtered_out: event.filtered_out,
+            }),
+            ("test" | "bench", Some(name)) => self.tests.push(TestOutcome { name, status, exec_time: event.exec_time, stdout: event.stdout }),
+            _ => {}
+        }
+    }
+}
+
+impl ParsedOutput for LibtestReport {
+    const NAME: &'static str = "libtest";
+
+    fn parse(result: &CommandResult) -> Result<Self> {
+        let test_line = Regex::new(r"^test (\S+)(?: - should panic)? \.\.\. (ok|FAILED|ignored)\b(?:.*<([\d.]+)s>)?")?;
+        let summary_line = Regex::new(
+            r"^test result: \w+\. (\d+) passed; (\d+) failed; (\d+) ignored; (\d+) measured; (\d+) filtered out",
+        )?;
+        let number = |captures: &regex::Captures, index: usize| captures[index].parse().unwrap_or(0);
+
+        let mut report = Self::default();
+        for line in result.stdout.lines() {
+            if let Some(value) = json_line(line) {
+                if let Ok(event) = serde_json::from_value::<LibtestEvent>(value) {
+                    report.push_event(event);
+                }
+            } else if let Some(captures) = test_line.captures(line) {
+                let status = match &captures[2] {
+                    "ok" => TestStatus::Ok,
+                    "FAILED" => TestStatus::Failed,
+                    _ => TestStatus::Ignored,
+                };
+                let exec_time = captures.get(3).and_then(|time| time.as_str().parse().ok());
+                report.tests.push(TestOutcome { name: captures[1].to_string(), status, exec_time, stdout: None });
+            } else if let Some(captures) = summary_line.captures(line) {
+                report.suites.push(SuiteSummary {
+                    passed: number(&captures, 1),
+                    failed: number(&captures, 2),
+                    ignored: number(&captures, 3),
+                    measured: number(&captures, 4),
+                    filtered_out: number(&captures, 5),
+                });
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GitBranch {
+    /// `None` before the first commit
+    pub oid: Option<String>,
+    /// `None` on a detached HEAD
+    pub head: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatusKind {
+    Changed,
+    Renamed,
+    Unmerged,
+    Untracked,
+    Ignored,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusEntry {
+    pub kind: StatusKind,
+    /// Staged and unstaged state letters (`M`, `A`, `D`, `R`, ...); `.` when unchanged
+    pub index: char,
+    pub worktree: char,
+    pub path: String,
+    /// Source of a rename or copy
+    pub original_path: Option<String>,
+}
+
+/// `git status --porcelain=v2`, with branch headers when `--branch` is given
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GitStatus {
+    pub branch: Option<GitBranch>,
+    pub entries: Vec<StatusEntry>,
+}
+
+impl GitStatus {
+    pub fn is_clean(&self) -> bool {
+        self.entries.iter().all(|entry| entry.kind == StatusKind::Ignored)
+    }
+
+    pub fn untracked(&self) -> impl Iterator<Item = &StatusEntry> {
+        self.entries.iter().filter(|entry| entry.kind == StatusKind::Untracked)
+    }
+
+    /// One-line description, e.g. `main...origin/main [ahead 1], 2 changed, 1 untracked`
+    pub fn summary(&self) -> String {
+        let mut summary = match &self.branch {
+            Some(branch) => {
+                let mut text = branch.head.clone().unwrap_or_else(|| "HEAD (detached)".to_string());
+                if let Some(upstream) = &branch.upstream {
+                    text.push_str(&format!("...{}", upstream));
+                }
+                match (branch.ahead, branch.behind) {
+                    (0, 0) => {}
+                    (ahead, 0) => text.push_str(&format!(" [ahead {}]", ahead)),
+                    (0, behind) => text.push_str(&format!(" [behind {}]", behind)),
+                    (ahead, behind) => text.push_str(&format!(" [ahead {}, behind {}]", ahead, behind)),
+                }
+                text
+            }
+            None => "unknown branch".to_string(),
+        };
+        let untracked = self.untracked().count();
+        let changed = self.entries.iter().filter(|entry| !matches!(entry.kind, StatusKind::Untracked | StatusKind::Ignored)).count();
+        if changed == 0 && untracked == 0 {
+            summary.push_str(", clean");
+        }
+        if changed > 0 {
+            summary.push_str(&format!(", {} changed", changed));
+        }
+        if untracked > 0 {
+            summary.push_str(&format!(", {} untracked", untracked));
+        }
+        summary
+    }
+}
+
+impl ParsedOutput for GitStatus {
+    const NAME: &'static str = "git-status";
+
+    fn parse(result: &CommandResult) -> Result<Self> {
+        let mut status = Self::default();
+        for line in result.stdout.lines().filter(|line| !line.is_empty()) {
+            let malformed = || anyhow!("Unexpected git status line: {}", line);
+            if let Some(header) = line.strip_prefix("# ") {
+                let branch = status.branch.get_or_insert_with(GitBranch::default);
+                let (key, value) = header.split_once(' ').ok_or_else(malformed)?;
+                match key {
+                    "branch.oid" => branch.oid = (value != "(initial)").then(|| value.to_string()),
+                    "branch.head" => branch.head = (value != "(detached)").then(|| value.to_string()),
+                    "branch.upstream" => branch.upstream = Some(value.to_string()),
+                    "branch.ab" => {
+                        let (ahead, behind) = value.split_once(' ').ok_or_else(malformed)?;
+                        branch.ahead = ahead.trim_start_matches('+').parse()?;
+                        branch.behind = behind.trim_start_matches('-').parse()?;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            let (kind, rest) = line.split_once(' ').ok_or_else(malformed)?;
+            // Fields before the path: XY, submodule state, modes, object names and the rename score
+            let (kind, skipped) = match kind {
+                "1" => (StatusKind::Changed, 7),
+                "2" => (StatusKind::Renamed, 8),
+                "u" => (StatusKind::Unmerged, 9),
+                "?" => (StatusKind::Untracked, 0),
+                "!" => (StatusKind::Ignored, 0),
+                _ => return Err(malformed()),
+            };
+            let fields: Vec<&str> = rest.splitn(skipped + 1, ' ').collect();
+            let path = fields.get(skipped).ok_or_else(malformed)?;
+            let (index, worktree) = match kind {
+                StatusKind::Untracked => ('?', '?'),
+                StatusKind::Ignored => ('!', '!'),
+                _ => {
+                    let mut xy = fields[0].chars();
+                    (xy.next().ok_or_else(malformed)?, xy.next().ok_or_else(malformed)?)
+                }
+            };
+            let (path, original_path) = match path.split_once('\t') {
+                Some((path, original)) if kind == StatusKind::Renamed => (path.to_string(), Some(original.to_string())),
+                _ => (path.to_string(), None),
+            };
+            status.entries.push(StatusEntry { kind, index, worktree, path, original_path });
+        }
+        Ok(status)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileStat {
+    /// `None` for binary files
+    pub added: Option<u32>,
+    pub deleted: Option<u32>,
+    /// Renames appear as `old => new` or `dir/{old => new}`
+    pub path: String,
+}
+
+/// `git diff --numstat` (also `git show`/`git log`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiffNumstat {
+    pub files: Vec<FileStat>,
+}
+
+impl DiffNumstat {
+    pub fn lines_added(&self) -> u32 {
+        self.files.iter().filter_map(|file| file.added).sum()
+    }
+
+    pub fn lines_deleted(&self) -> u32 {
+        self.files.iter().filter_map(|file| file.deleted).sum()
+    }
+
+    pub fn paths(&self) -> Vec<String> {
+        self.files.iter().map(|file| file.path.clone()).collect()
+    }
+}
+
+impl ParsedOutput for DiffNumstat {
+    const NAME: &'static str = "git-numstat";
+
+    fn parse(result: &CommandResult) -> Result<Self> {
+        let count = |field: &str| -> Result<Option<u32>> {
+            match field {
+                "-" => Ok(None),
+                field => Ok(Some(field.parse()?)),
+            }
+        };
+        let mut files = Vec::new();
+        for line in result.stdout.lines() {
+            let mut fields = line.splitn(3, '\t');
+            // `git log --numstat` interleaves commit headers; only stat lines have two tabs
+            let (Some(added), Some(deleted), Some(path)) = (fields.next(), fields.next(), fields.next()) else { continue };
+            files.push(FileStat { added: count(added)?, deleted: count(deleted)?, path: path.to_string() });
+        }
+        Ok(Self { files })
+    }
+}
+
+/// A `file:line:col: message` line, as printed by compilers, linters and `grep -n`-like tools
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: u32,
+    pub column: Option<u32>,
+    /// `error`, `warning`, ... when the message starts with one
+    pub severity: Option<String>,
+    pub message: String,
+}
+
+/// Generic diagnostics found in stdout and stderr of any command
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostics {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParsedOutput for Diagnostics {
+    const NAME: &'static str = "diagnostics";
+
+    fn parse(result: &CommandResult) -> Result<Self> {
+        // A path must contain a non-digit so timestamps such as 12:30:45 are not taken for locations
+        let location = Regex::new(
+            r"^(?:-->\s*)?([^\s:]*[^\d\s:][^\s:]*):(\d+)(?::(\d+))?:?\s*(?:(error|warning|note|help|info)(?:\[[^\]]*\])?:\s*)?(.*)$",
+        )?;
+        let diagnostics = result.stdout.lines()
+            .chain(result.stderr.lines())
+            .filter_map(|line| location.captures(line.trim()))
+            .filter_map(|captures| {
+                Some(Diagnostic {
+                    file: captures[1].to_string(),
+                    line: captures[2].parse().ok()?,
+                    column: captures.get(3).and_then(|column| column.as_str().parse().ok()),
+                    severity: captures.get(4).map(|severity| severity.as_str().to_string()),
+                    message: captures[5].trim().to_string(),
+                })
+            })
+            .collect();
+        Ok(Self { diagnostics })
+    }
+}
+
+/// A stdout line holding a JSON object
+fn json_line(line: &str) -> Option<serde_json::Value> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return None;
+    }
+    serde_json::from_str(line).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(command: &str, stdout: &str) -> CommandResult {
+        CommandResult {
+            command: command.to_string(),
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+            exit_code: 0,
+            duration_ms: 1,
+            timed_out: false,
+        }
+    }
+
+    #[test]
+    fn test_parsers_are_registered_by_command() {
+        assert_eq!(parsers_for("cargo clippy --message-format=json"), vec![CargoMessages::NAME, Diagnostics::NAME]);
+        assert_eq!(parsers_for("cd repo && cargo test --message-format json -- --format json"), vec![
+            CargoMessages::NAME,
+            LibtestReport::NAME,
+            Diagnostics::NAME,
+        ]);
+        assert_eq!(parsers_for("git status --porcelain=v2 --branch"), vec![GitStatus::NAME, Diagnostics::NAME]);
+        assert_eq!(parsers_for("git -C repo diff --numstat HEAD~1"), vec![DiffNumstat::NAME, Diagnostics::NAME]);
+        assert_eq!(parsers_for("git status --porcelain"), vec![Diagnostics::NAME]);
+
+        let error = output("git status --short", "").parsed::<GitStatus>().unwrap_err();
+        assert!(error.to_string().contains("No git-status parser is registered"));
+    }
+
+    #[test]
+    fn test_cargo_messages() {
+        let stdout = concat!(
+            r#"{"reason":"compiler-artifact","package_id":"dep 0.1.0","target":{"kind":["lib"],"name":"dep"},"fresh":true}"#, "\n",
+            r#"{"reason":"compiler-message","package_id":"app 0.1.0","target":{"kind":["bin"],"name":"app"},"message":{"message":"unused variable: `x`","level":"warning","code":{"code":"unused_variables","explanation":null},"spans":[{"file_name":"src/main.rs","line_start":2,"line_end":2,"column_start":9,"column_end":10,"is_primary":true,"label":null,"text":[]}],"children":[],"rendered":"warning: unused variable"}}"#, "\n",
+            r#"{"reason":"compiler-message","package_id":"app 0.1.0","target":{"kind":["bin"],"name":"app"},"message":{"message":"1 warning emitted","level":"warning","code":null,"spans":[],"children":[],"rendered":null}}"#, "\n",
+            "running 0 tests\n",
+            r#"{"reason":"build-finished","success":true}"#, "\n",
+        );
+        let messages: CargoMessages = output("cargo check --message-format=json", stdout).parsed().unwrap();
+        assert_eq!(messages.messages.len(), 4);
+        let warnings: Vec<_> = messages.warnings().collect();
+        assert_eq!(warnings.len(), 1);
+        let span = warnings[0].primary_span().unwrap();
+        assert_eq!((span.file_name.as_str(), span.line_start, span.column_start), ("src/main.rs", 2, 9));
+        assert_eq!(messages.errors().count(), 0);
+        assert_eq!(messages.build_succeeded(), Some(true));
+    }
+
+    #[test]
+    fn test_libtest_json_and_human_output() {
+        let json = concat!(
+            r#"{ "type": "suite", "event": "started", "test_count": 3 }"#, "\n",
+            r#"{ "type": "test", "event": "started", "name": "tests::a" }"#, "\n",
+            r#"{ "type": "test", "name": "tests::a", "event": "ok", "exec_time": 0.002 }"#, "\n",
+            r#"{ "type": "test", "name": "tests::b", "event": "failed", "stdout": "assertion failed\n" }"#, "\n",
+            r#"{ "type": "test", "name": "tests::c", "event": "ignored" }"#, "\n",
+            r#"{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "ignored": 1, "measured": 0, "filtered_out": 2, "exec_time": 0.01 }"#, "\n",
+        );
+        let report: LibtestReport = output("cargo test -- -Z unstable-options --format json", json).parsed().unwrap();
+        assert_eq!((report.passed(), report.failed(), report.ignored()), (1, 1, 1));
+        assert_eq!(report.failing_tests(), vec!["tests::b"]);
+        assert_eq!(report.tests[1].stdout.as_deref(), Some("assertion failed\n"));
+        assert_eq!(report.suites[0].filtered_out, 2);
+
+        let human = "running 3 tests\ntest tests::a ... ok <0.002s>\ntest tests::b ... FAILED\ntest tests::c ... ignored, slow\n\n\
+                     failures:\n    tests::b\n\ntest result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.01s\n\n\
+                     running 1 test\ntest it_works ... ok\n\ntest result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out\n";
+        let report: LibtestReport = output("cargo test", human).parsed().unwrap();
+        assert_eq!((report.passed(), report.failed(), report.ignored()), (2, 1, 1));
+        assert_eq!(report.failing_tests(), vec!["tests::b"]);
+        assert_eq!(report.tests[0].exec_time, Some(0.002));
+    }
+
+    #[test]
+    fn test_git_status_porcelain_v2() {
+        let stdout = "# branch.oid 1234abcd\n# branch.head main\n# branch.upstream origin/main\n# branch.ab +2 -0\n\
+                      1 .M N... 100644 100644 100644 aaaa bbbb src/my file.rs\n\
+                      2 R. N... 100644 100644 100644 aaaa bbbb R100 src/new.rs\tsrc/old.rs\n\
+                      u UU N... 100644 100644 100644 100644 aaaa bbbb cccc Cargo.lock\n\
+                      ? notes.txt\n! target/\n";
+        let status: GitStatus = output("git status --porcelain=v2 --branch", stdout).parsed().unwrap();
+        let branch = status.branch.as_ref().unwrap();
+        assert_eq!((branch.head.as_deref(), branch.ahead, branch.behind), (Some("main"), 2, 0));
+        assert_eq!(status.entries.len(), 5);
+        assert_eq!((status.entries[0].worktree, status.entries[0].path.as_str()), ('M', "src/my file.rs"));
+        assert_eq!(status.entries[1].original_path.as_deref(), Some("src/old.rs"));
+        assert_eq!(status.entries[2].kind, StatusKind::Unmerged);
+        assert_eq!(status.untracked().count(), 1);
+        assert!(!status.is_clean());
+        assert_eq!(status.summary(), "main...origin/main [ahead 2], 3 changed, 1 untracked");
+
+        let clean: GitStatus = output("git status --porcelain=v2 -b", "# branch.oid (initial)\n# branch.head main\n").parsed().unwrap();
+        assert!(clean.is_clean());
+        assert_eq!(clean.branch.unwrap().oid, None);
+        assert!(output("git status --porcelain=v2", "X bogus\n").parsed::<GitStatus>().is_err());
+    }
+
+    #[test]
+    fn test_diff_numstat_and_diagnostics() {
+        let stdout = "10\t5\tsrc/main.rs\n-\t-\tassets/logo.png\n3\t0\tsrc/{old => new}.rs\n";
+        let numstat: DiffNumstat = output("git diff --numstat", stdout).parsed().unwrap();
+        assert_eq!((numstat.lines_added(), numstat.lines_deleted()), (13, 5));
+        assert_eq!(numstat.files[1].added, None);
+        assert_eq!(numstat.paths()[2], "src/{old => new}.rs");
+
+        let mut result = output("eslint --format unix src", "src/app.ts:12:5: warning: Missing semicolon\n12:30:45 done\n");
+        result.stderr = "error[E0308]: mismatched types\n  --> src/lib.rs:3:9\nlib/util.py:7: error: bad indent\n".to_string();
+        let diagnostics: Diagnostics = result.parsed().unwrap();
+        let found: Vec<_> = diagnostics.diagnostics.iter()
+            .map(|d| (d.file.as_str(), d.line, d.column, d.severity.as_deref()))
+            .collect();
+        assert_eq!(found, vec![
+            ("src/app.ts", 12, Some(5), Some("warning")),
+            ("src/lib.rs", 3, Some(9), None),
+            ("lib/util.py", 7, None, Some("error")),
+        ]);
+        assert_eq!(diagnostics.diagnostics[0].message, "Missing semicolon");
+    }
+}
@@ -0,0 +1,218 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+fn default_writable() -> Vec<String> {
+    vec!["target".to_string()]
+}
+
+fn default_cpu_seconds() -> u64 {
+    600
+}
+
+fn default_memory_mb() -> u64 {
+    8192
+}
+
+/// Limits applied to commands run by a sandboxed `TerminalController`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Refuse to run commands when isolation is unavailable instead of running them with limits only
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub network: bool,
+    /// Paths that stay writable, relative to the repository root unless absolute.
+    /// Everything else is read-only; `/tmp` is a private scratch directory.
+    #[serde(default = "default_writable")]
+    pub writable: Vec<String>,
+    /// CPU time per process (`ulimit -t`)
+    #[serde(default = "default_cpu_seconds")]
+    pub cpu_seconds: u64,
+    /// Address space per process (`ulimit -v`)
+    #[serde(default = "default_memory_mb")]
+    pub memory_mb: u64,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            required: false,
+            network: false,
+            writable: default_writable(),
+            cpu_seconds: default_cpu_seconds(),
+            memory_mb: default_memory_mb(),
+        }
+    }
+}
+
+/// Runs commands under bubblewrap: the filesystem is mounted read-only apart from the
+/// writable paths, and network, processes and IPC are unshared. Without a working
+/// bubblewrap (not Linux, no user namespaces) only the resource limits apply.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    config: SandboxConfig,
+    root: PathBuf,
+    /// `None` when isolation is unavailable
+    bwrap: Option<PathBuf>,
+}
+
+impl Sandbox {
+    /// Sandbox for commands working on the repository at `root`; `None` when disabled
+    pub fn new(config: SandboxConfig, root: impl Into<PathBuf>) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let bwrap = probe_bwrap();
+        if bwrap.is_none() {
+            log::warn!(
+                "bubblewrap is unavailable; sandboxed commands {}",
+                if config.required { "will be refused" } else { "only get resource limits" }
+            );
+        }
+        Some(Self { config, root: root.into(), bwrap })
+    }
+
+    pub fn is_isolated(&self) -> bool {
+        self.bwrap.is_some()
+    }
+
+    /// Fails when isolation is required but unavailable
+    pub fn check(&self) -> Result<()> {
+        if self.config.required && self.bwrap.is_none() {
+            return Err(anyhow!("Sandbox is required but bubblewrap is unavailable"));
+        }
+        Ok(())
+    }
+
+    /// Program and arguments that run `command` in `cwd` inside the sandbox
+    pub fn command_line(&self, command: &str, cwd: &Path) -> Result<Vec<String>> {
+        self.check()?;
+        let limited = format!(
+            "ulimit -t {} -v {} 2>/dev/null\n{}",
+            self.config.cpu_seconds,
+            self.config.memory_mb * 1024,
+            command
+        );
+
+        let Some(bwrap) = &self.bwrap else {
+            return Ok(vec!["bash".to_string(), "-c".to_string(), limited]);
+        };
+
+        let mut args = vec![
+            bwrap.to_string_lossy().to_string(),
+            "--die-with-parent".to_string(),
+            "--new-session".to_string(),
+            "--unshare-all".to_string(),
+        ];
+        if self.config.network {
+            args.push("--share-net".to_string());
+        }
+        let root = self.root.to_string_lossy().to_string();
+        args.extend(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"].map(String::from));
+        // The repository may itself live under /tmp, which the scratch mount just hid
+        args.extend(["--ro-bind".to_string(), root.clone(), root]);
+
+        for path in &self.config.writable {
+            let path = self.root.join(path);
+            if !path.exists() && path.starts_with(&self.root) {
+                std::fs::create_dir_all(&path)?;
+            }
+            if !path.exists() {
+                log::warn!("Sandbox path {} does not exist and stays unavailable", path.display());
+                continue;
+            }
+            let path = path.to_string_lossy().to_string();
+            args.extend(["--bind".to_string(), path.clone(), path]);
+        }
+
+        args.extend(["--chdir".to_string(), cwd.to_string_lossy().to_string()]);
+        args.extend(["bash".to_string(), "-c".to_string(), limited]);
+        Ok(args)
+    }
+}
+
+/// Path of a bubblewrap that can actually create namespaces here
+fn probe_bwrap() -> Option<PathBuf> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let status = Command::new("bwrap")
+        .args(["--ro-bind", "/", "/", "--unshare-all", "--die-with-parent", "true"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok()?;
+    status.success().then(|| PathBuf::from("bwrap"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desktop::TerminalController;
+    use tempfile::TempDir;
+
+    fn config() -> SandboxConfig {
+        SandboxConfig { enabled: true, cpu_seconds: 7, memory_mb: 512, ..Default::default() }
+    }
+
+    #[test]
+    fn test_bwrap_arguments() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let sandbox = Sandbox { config: config(), root: root.to_path_buf(), bwrap: Some(PathBuf::from("bwrap")) };
+
+        let args = sandbox.command_line("cargo build", root).unwrap();
+        let joined = args.join(" ");
+        let target = root.join("target").to_string_lossy().to_string();
+        assert!(root.join("target").is_dir());
+        assert!(joined.starts_with("bwrap --die-with-parent --new-session --unshare-all --ro-bind / /"));
+        assert!(joined.contains(&format!("--bind {} {}", target, target)));
+        assert!(!joined.contains("--share-net"));
+        assert_eq!(args[args.len() - 3..], ["bash".to_string(), "-c".to_string(), "ulimit -t 7 -v 524288 2>/dev/null\ncargo build".to_string()]);
+
+        let networked = Sandbox { config: SandboxConfig { network: true, ..config() }, ..sandbox };
+        assert!(networked.command_line("cargo fetch", root).unwrap().contains(&"--share-net".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_fallback_applies_limits_or_refuses() {
+        let temp_dir = TempDir::new().unwrap();
+        let fallback = Sandbox { config: config(), root: temp_dir.path().to_path_buf(), bwrap: None };
+        let terminal = TerminalController::new().with_sandbox(fallback.clone());
+
+        let result = terminal.execute_command("ulimit -t; ulimit -v").await.unwrap();
+        assert_eq!(result.stdout, "7\n524288\n");
+
+        let strict = Sandbox { config: SandboxConfig { required: true, ..config() }, ..fallback };
+        let terminal = TerminalController::new().with_sandbox(strict);
+        let error = terminal.execute_command("echo hi").await.unwrap_err();
+        assert!(error.to_string().contains("bubblewrap is unavailable"));
+    }
+
+    #[tokio::test]
+    async fn test_isolation_when_available() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let sandbox = Sandbox::new(config(), &root).unwrap();
+        if !sandbox.is_isolated() {
+            eprintln!("bubblewrap unavailable, skipping isolation checks");
+            return;
+        }
+
+        let terminal = TerminalController::new().with_sandbox(sandbox);
+        let command = format!(
+            "cd {} && touch target/ok && ! touch outside 2>/dev/null && ! (exec 3<>/dev/tcp/1.1.1.1/53) 2>/dev/null",
+            root.display()
+        );
+        let result = terminal.execute_command(&command).await.unwrap();
+        assert_eq!(result.exit_code, 0, "{}", result.stderr);
+        assert!(root.join("target/ok").exists());
+        assert!(!root.join("outside").exists());
+    }
+}
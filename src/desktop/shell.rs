@@ -0,0 +1,665 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+
+/// Parsed command line: pipelines joined by `;`, `&&`, `||`, `&` or newlines
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Script {
+    pub pipelines: Vec<Pipeline>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline {
+    pub commands: Vec<ShellCommand>,
+    pub negated: bool,
+    /// How this pipeline is joined to the next one
+    pub connector: Connector,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connector {
+    Sequence,
+    And,
+    Or,
+    Background,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShellCommand {
+    Simple(SimpleCommand),
+    /// `( ... )`, run in a child shell
+    Subshell { body: Script, redirects: Vec<Redirect> },
+    /// `{ ...; }`, run in the current shell
+    Group { body: Script, redirects: Vec<Redirect> },
+}
+
+/// A program with its arguments. Words have quotes and escapes removed but are not expanded,
+/// so `~` and `$HOME` appear literally.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SimpleCommand {
+    /// Leading `NAME=value` words
+    pub assignments: Vec<String>,
+    pub words: Vec<String>,
+    pub redirects: Vec<Redirect>,
+    /// `$(...)` and backtick substitutions found in any word
+    pub substitutions: Vec<Script>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirect {
+    pub fd: Option<u32>,
+    pub operator: String,
+    pub target: String,
+}
+
+impl SimpleCommand {
+    /// Program name without its directory, e.g. `rm` for `/bin/rm`
+    pub fn program(&self) -> Option<&str> {
+        self.words.first().map(|word| word.rsplit('/').next().unwrap_or(word))
+    }
+
+    pub fn args(&self) -> &[String] {
+        self.words.get(1..).unwrap_or(&[])
+    }
+}
+
+impl fmt::Display for SimpleCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redirects = self.redirects.iter().map(|r| {
+            format!("{}{}{}", r.fd.map(|fd| fd.to_string()).unwrap_or_default(), r.operator, r.target)
+        });
+        let parts: Vec<String> = self.assignments.iter().cloned()
+            .chain(self.words.iter().cloned())
+            .chain(redirects)
+            .collect();
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+impl Script {
+    /// Visit every simple command, including those in subshells, groups and substitutions.
+    /// The second argument is the command piping into this one, if any.
+    pub fn visit<'a>(&'a self, visitor: &mut impl FnMut(&'a SimpleCommand, Option<&'a SimpleCommand>)) {
+        for pipeline in &self.pipelines {
+            let mut previous = None;
+            for command in &pipeline.commands {
+                match command {
+                    ShellCommand::Simple(simple) => {
+                        visitor(simple, previous);
+                        for substitution in &simple.substitutions {
+                            substitution.visit(visitor);
+                        }
+                        previous = Some(simple);
+                    }
+                    ShellCommand::Subshell { body, .. } | ShellCommand::Group { body, .. } => {
+                        body.visit(visitor);
+                        previous = None;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pipelines.is_empty()
+    }
+}
+
+/// Parse a bash command line. Control keywords (`if`, `while`, `for`, ...) are looked through
+/// so the commands inside them are still seen; `case` and function definitions are rejected.
+pub fn parse(input: &str) -> Result<Script> {
+    let tokens = Lexer::new(input).tokenize()?;
+    let mut parser = Parser { tokens, position: 0 };
+    let script = parser.script(None)?;
+    match parser.peek() {
+        None => Ok(script),
+        Some(token) => Err(anyhow!("Unexpected {}", token)),
+    }
+}
+
+/// Quote a word so bash and `parse` read it back unchanged, e.g. `'a b'` for `a b`.
+/// Words made only of characters bash treats literally are left as they are.
+pub fn quote(word: &str) -> String {
+    let literal = |c: char| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c);
+    if !word.is_empty() && word.chars().all(literal) {
+        return word.to_string();
+    }
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Word {
+    text: String,
+    substitutions: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(Word),
+    Operator(&'static str),
+    Redirect { fd: Option<u32>, operator: &'static str },
+    Newline,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "'{}'", word.text),
+            Token::Operator(op) | Token::Redirect { operator: op, .. } => write!(f, "'{}'", op),
+            Token::Newline => write!(f, "newline"),
+        }
+    }
+}
+
+/// Longest operators first so `&&` wins over `&`
+const OPERATORS: &[&str] = &["&&", "||", ";;", "|&", ";", "&", "|", "(", ")"];
+const REDIRECTS: &[&str] = &["<<<", "<<-", "&>>", "<<", "<>", ">>", "&>", ">&", "<&", ">|", ">", "<"];
+/// Keywords that only introduce the command after them
+const PREFIX_KEYWORDS: &[&str] = &["if", "then", "else", "elif", "while", "until", "do"];
+/// Keywords that close a construct and stand in for an empty command
+const CLOSING_KEYWORDS: &[&str] = &["fi", "done"];
+
+struct Lexer {
+    chars: Vec<char>,
+    position: usize,
+    /// Here-document delimiters whose bodies start after the next newline, with `<<-` tab stripping
+    heredocs: Vec<(String, bool)>,
+    expect_delimiter: Option<bool>,
+}
+
+impl Lexer {
+    fn new(input: &str) -> Self {
+        Self { chars: input.chars().collect(), position: 0, heredocs: Vec::new(), expect_delimiter: None }
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        text.chars().enumerate().all(|(i, c)| self.peek_at(i) == Some(c))
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>> {
+        let mut tokens = Vec::new();
+        while let Some(token) = self.next_token()? {
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>> {
+        loop {
+            match self.peek_at(0) {
+                Some(' ' | '\t') => self.position += 1,
+                Some('\\') if self.peek_at(1) == Some('\n') => self.position += 2,
+                Some('#') => {
+                    while self.peek_at(0).is_some_and(|c| c != '\n') {
+                        self.position += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        let Some(c) = self.peek_at(0) else { return Ok(None) };
+        if c == '\n' {
+            self.position += 1;
+            self.skip_heredoc_bodies();
+            return Ok(Some(Token::Newline));
+        }
+
+        // `2>` and friends: a file descriptor number directly before a redirection
+        let digits = self.chars[self.position..].iter().take_while(|c| c.is_ascii_digit()).count();
+        if digits > 0 {
+            let after = self.position + digits;
+            if matches!(self.chars.get(after), Some('<' | '>')) {
+                let fd: String = self.chars[self.position..after].iter().collect();
+                self.position = after;
+                let operator = self.redirect_operator().expect("a redirect follows the digits");
+                return Ok(Some(Token::Redirect { fd: fd.parse().ok(), operator }));
+            }
+        }
+        if let Some(operator) = self.redirect_operator() {
+            return Ok(Some(Token::Redirect { fd: None, operator }));
+        }
+        if let Some(operator) = OPERATORS.iter().find(|op| self.starts_with(op)) {
+            self.position += operator.len();
+            return Ok(Some(Token::Operator(operator)));
+        }
+
+        let word = self.word()?;
+        if let Some(strip_tabs) = self.expect_delimiter.take() {
+            self.heredocs.push((word.text.clone(), strip_tabs));
+        }
+        Ok(Some(Token::Word(word)))
+    }
+
+    fn redirect_operator(&mut self) -> Option<&'static str> {
+        let operator = REDIRECTS.iter().find(|op| self.starts_with(op))?;
+        self.position += operator.len();
+        if *operator == "<<" || *operator == "<<-" {
+            self.expect_delimiter = Some(*operator == "<<-");
+        }
+        Some(operator)
+    }
+
+    fn skip_heredoc_bodies(&mut self) {
+        for (delimiter, strip_tabs) in std::mem::take(&mut self.heredocs) {
+            while self.position < self.chars.len() {
+                let end = self.chars[self.position..].iter()
+                    .position(|c| *c == '\n')
+                    .map_or(self.chars.len(), |i| self.position + i);
+                let line: String = self.chars[self.position..end].iter().collect();
+                self.position = (end + 1).min(self.chars.len());
+                let line = if strip_tabs { line.trim_start_matches('\t') } else { line.as_str() };
+                if line == delimiter {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn word(&mut self) -> Result<Word> {
+        let mut word = Word { text: String::new(), substitutions: Vec::new() };
+        while let Some(c) = self.peek_at(0) {
+            match c {
+                ' ' | '\t' | '\n' | ';' | '&' | '|' | '(' | ')' | '<' | '>' => break,
+                '\\' => {
+                    self.position += 1;
+                    match self.peek_at(0) {
+                        Some('\n') => self.position += 1,
+                        Some(escaped) => {
+                            word.text.push(escaped);
+                            self.position += 1;
+                        }
+                        None => {}
+                    }
+                }
+                '\'' => {
+                    self.position += 1;
+                    let end = self.find('\'').ok_or_else(|| anyhow!("Unterminated single quote"))?;
+                    word.text.extend(&self.chars[self.position..end]);
+                    self.position = end + 1;
+                }
+                '"' => self.double_quoted(&mut word)?,
+                '$' => self.dollar(&mut word)?,
+                '`' => self.backtick(&mut word)?,
+                _ => {
+                    word.text.push(c);
+                    self.position += 1;
+                }
+            }
+        }
+        Ok(word)
+    }
+
+    fn find(&self, target: char) -> Option<usize> {
+        self.chars[self.position..].iter().position(|c| *c == target).map(|i| self.position + i)
+    }
+
+    fn double_quoted(&mut self, word: &mut Word) -> Result<()> {
+        self.position += 1;
+        loop {
+            match self.peek_at(0) {
+                None => return Err(anyhow!("Unterminated double quote")),
+                Some('"') => {
+                    self.position += 1;
+                    return Ok(());
+                }
+                Some('\\') => {
+                    match self.peek_at(1) {
+                        Some(escaped @ ('$' | '`' | '"' | '\\')) => word.text.push(escaped),
+                        Some('\n') => {}
+                        Some(other) => {
+                            word.text.push('\\');
+                            word.text.push(other);
+                        }
+                        None => return Err(anyhow!("Unterminated double quote")),
+                    }
+                    self.position += 2;
+                }
+                Some('$') => self.dollar(word)?,
+                Some('`') => self.backtick(word)?,
+                Some(c) => {
+                    word.text.push(c);
+                    self.position += 1;
+                }
+            }
+        }
+    }
+
+    /// `$(...)` is a substitution; `$((...))` and `${...}` are kept as text
+    fn dollar(&mut self, word: &mut Word) -> Result<()> {
+        let start = self.position;
+        match self.peek_at(1) {
+            Some('(') if self.peek_at(2) == Some('(') => {
+                self.position += 1;
+                self.balanced('(', ')')?;
+            }
+            Some('(') => {
+                self.position += 1;
+                self.balanced('(', ')')?;
+                word.substitutions.push(self.chars[start + 2..self.position - 1].iter().collect());
+            }
+            Some('{') => {
+                self.position += 1;
+                self.balanced('{', '}')?;
+            }
+            _ => self.position += 1,
+        }
+        word.text.extend(&self.chars[start..self.position]);
+        Ok(())
+    }
+
+    /// Skip from an opening bracket to its match, stepping over quoted text
+    fn balanced(&mut self, open: char, close: char) -> Result<()> {
+        let mut depth = 0;
+        while let Some(c) = self.peek_at(0) {
+            self.position += 1;
+            match c {
+                '\\' => self.position += 1,
+                '\'' => {
+                    let end = self.find('\'').ok_or_else(|| anyhow!("Unterminated single quote"))?;
+                    self.position = end + 1;
+                }
+                '"' => {
+                    while let Some(inner) = self.peek_at(0) {
+                        self.position += if inner == '\\' { 2 } else { 1 };
+                        if inner == '"' {
+                            break;
+                        }
+                    }
+                }
+                _ if c == open => depth += 1,
+                _ if c == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(anyhow!("Unterminated '{}'", open))
+    }
+
+    fn backtick(&mut self, word: &mut Word) -> Result<()> {
+        let start = self.position;
+        self.position += 1;
+        let mut inner = String::new();
+        loop {
+            match self.peek_at(0) {
+                None => return Err(anyhow!("Unterminated backtick")),
+                Some('`') => break,
+                Some('\\') if self.peek_at(1).is_some() => {
+                    inner.push(self.chars[self.position + 1]);
+                    self.position += 2;
+                }
+                Some(c) => {
+                    inner.push(c);
+                    self.position += 1;
+                }
+            }
+        }
+        self.position += 1;
+        word.text.extend(&self.chars[start..self.position]);
+        word.substitutions.push(inner);
+        Ok(())
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_word(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Word(word)) => Some(&word.text),
+            _ => None,
+        }
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Pipelines up to the end of input or the `closing` token of a subshell or group
+    fn script(&mut self, closing: Option<&str>) -> Result<Script> {
+        let mut script = Script::default();
+        loop {
+            while matches!(self.peek(), Some(Token::Newline | Token::Operator(";"))) {
+                self.position += 1;
+            }
+            match (self.peek(), closing) {
+                (None, _) => break,
+                (Some(Token::Operator(")")), Some(")")) => break,
+                (Some(Token::Word(word)), Some("}")) if word.text == "}" => break,
+                _ => {}
+            }
+
+            let mut pipeline = self.pipeline()?;
+            pipeline.connector = match self.peek() {
+                Some(Token::Operator("&&")) => Connector::And,
+                Some(Token::Operator("||")) => Connector::Or,
+                Some(Token::Operator("&")) => Connector::Background,
+                _ => Connector::Sequence,
+            };
+            if pipeline.connector != Connector::Sequence {
+                self.position += 1;
+            }
+            if matches!(pipeline.connector, Connector::And | Connector::Or) {
+                while self.peek() == Some(&Token::Newline) {
+                    self.position += 1;
+                }
+                if self.peek().is_none() {
+                    return Err(anyhow!("Expected a command after '{}'", if pipeline.connector == Connector::And { "&&" } else { "||" }));
+                }
+            }
+            script.pipelines.push(pipeline);
+        }
+        Ok(script)
+    }
+
+    fn pipeline(&mut self) -> Result<Pipeline> {
+        let negated = self.peek_word() == Some("!");
+        if negated {
+            self.position += 1;
+        }
+
+        let mut commands = vec![self.command()?];
+        while matches!(self.peek(), Some(Token::Operator("|" | "|&"))) {
+            self.position += 1;
+            while self.peek() == Some(&Token::Newline) {
+                self.position += 1;
+            }
+            commands.push(self.command()?);
+        }
+        Ok(Pipeline { commands, negated, connector: Connector::Sequence })
+    }
+
+    fn command(&mut self) -> Result<ShellCommand> {
+        if self.peek() == Some(&Token::Operator("(")) {
+            self.position += 1;
+            let body = self.script(Some(")"))?;
+            self.expect(|t| t == &Token::Operator(")"), "')'")?;
+            let redirects = self.redirects()?;
+            return Ok(ShellCommand::Subshell { body, redirects });
+        }
+
+        match self.peek_word() {
+            Some("{") => {
+                self.position += 1;
+                let body = self.script(Some("}"))?;
+                self.expect(|t| matches!(t, Token::Word(w) if w.text == "}"), "'}'")?;
+                let redirects = self.redirects()?;
+                return Ok(ShellCommand::Group { body, redirects });
+            }
+            Some(keyword) if PREFIX_KEYWORDS.contains(&keyword) => {
+                self.position += 1;
+                return self.command();
+            }
+            Some(keyword) if CLOSING_KEYWORDS.contains(&keyword) => {
+                self.position += 1;
+                let redirects = self.redirects()?;
+                return Ok(ShellCommand::Simple(SimpleCommand { redirects, ..Default::default() }));
+            }
+            Some(keyword @ ("case" | "function" | "select")) => {
+                return Err(anyhow!("'{}' is not supported", keyword));
+            }
+            Some("for") => {
+                // The loop header only matters for substitutions in its word list
+                let mut header = self.simple_command()?;
+                header.words.clear();
+                return Ok(ShellCommand::Simple(header));
+            }
+            _ => {}
+        }
+
+        let command = self.simple_command()?;
+        if command.words.len() == 1 && self.peek() == Some(&Token::Operator("(")) {
+            return Err(anyhow!("Function definitions are not supported"));
+        }
+        Ok(ShellCommand::Simple(command))
+    }
+
+    fn simple_command(&mut self) -> Result<SimpleCommand> {
+        let mut command = SimpleCommand::default();
+        loop {
+            match self.peek() {
+                Some(Token::Word(_)) => {
+                    let Some(Token::Word(word)) = self.advance() else { unreachable!() };
+                    for source in &word.substitutions {
+                        command.substitutions.push(parse(source)?);
+                    }
+                    if command.words.is_empty() && is_assignment(&word.text) {
+                        command.assignments.push(word.text);
+                    } else {
+                        command.words.push(word.text);
+                    }
+                }
+                Some(Token::Redirect { .. }) => command.redirects.push(self.redirect()?),
+                _ => break,
+            }
+        }
+
+        if command.words.is_empty() && command.assignments.is_empty() && command.redirects.is_empty() {
+            return Err(match self.peek() {
+                Some(token) => anyhow!("Expected a command before {}", token),
+                None => anyhow!("Expected a command at the end of input"),
+            });
+        }
+        Ok(command)
+    }
+
+    fn redirects(&mut self) -> Result<Vec<Redirect>> {
+        let mut redirects = Vec::new();
+        while matches!(self.peek(), Some(Token::Redirect { .. })) {
+            redirects.push(self.redirect()?);
+        }
+        Ok(redirects)
+    }
+
+    fn redirect(&mut self) -> Result<Redirect> {
+        let Some(Token::Redirect { fd, operator }) = self.advance() else { unreachable!() };
+        match self.advance() {
+            Some(Token::Word(word)) => Ok(Redirect { fd, operator: operator.to_string(), target: word.text }),
+            _ => Err(anyhow!("Expected a target after '{}'", operator)),
+        }
+    }
+
+    fn expect(&mut self, matches: impl Fn(&Token) -> bool, expected: &str) -> Result<()> {
+        match self.advance() {
+            Some(token) if matches(&token) => Ok(()),
+            Some(token) => Err(anyhow!("Expected {} but found {}", expected, token)),
+            None => Err(anyhow!("Expected {} at the end of input", expected)),
+        }
+    }
+}
+
+fn is_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simple_commands(input: &str) -> Vec<String> {
+        let script = parse(input).unwrap();
+        let mut commands = Vec::new();
+        script.visit(&mut |command, _| commands.push(command.to_string()));
+        commands
+    }
+
+    #[test]
+    fn test_pipelines_and_redirections() {
+        let script = parse("FOO=1 cargo test 2>&1 | tee 'out file.log' && echo \"done $USER\" > /tmp/x; sleep 1 &").unwrap();
+        assert_eq!(script.pipelines.len(), 3);
+        assert_eq!(script.pipelines[0].connector, Connector::And);
+        assert_eq!(script.pipelines[2].connector, Connector::Background);
+
+        let ShellCommand::Simple(cargo) = &script.pipelines[0].commands[0] else { panic!("expected a simple command") };
+        assert_eq!(cargo.assignments, vec!["FOO=1"]);
+        assert_eq!(cargo.program(), Some("cargo"));
+        assert_eq!(cargo.redirects, vec![Redirect { fd: Some(2), operator: ">&".to_string(), target: "1".to_string() }]);
+
+        let ShellCommand::Simple(tee) = &script.pipelines[0].commands[1] else { panic!("expected a simple command") };
+        assert_eq!(tee.args(), ["out file.log"]);
+
+        let ShellCommand::Simple(echo) = &script.pipelines[1].commands[0] else { panic!("expected a simple command") };
+        assert_eq!(echo.words, vec!["echo", "done $USER"]);
+        assert_eq!(echo.redirects[0].target, "/tmp/x");
+    }
+
+    #[test]
+    fn test_nested_commands_are_visited() {
+        assert_eq!(
+            simple_commands("(cd src && rm -rf ~) ; { git status; } >> log; echo $(whoami) `date +%s`"),
+            vec!["cd src", "rm -rf ~", "git status", "echo $(whoami) `date +%s`", "whoami", "date +%s"],
+        );
+        assert_eq!(
+            simple_commands("if test -f x; then /bin/rm x; fi\nfor f in $(ls); do echo $f; done"),
+            vec!["test -f x", "/bin/rm x", "", "", "ls", "echo $f", ""],
+        );
+        // Here-document bodies are data, not commands
+        assert_eq!(simple_commands("cat <<'EOF' > notes\nrm -rf /\nEOF\necho ok"), vec!["cat <<EOF >notes", "echo ok"]);
+        assert_eq!(simple_commands("echo $((1 + 2)) ${HOME} # rm -rf /"), vec!["echo $((1 + 2)) ${HOME}"]);
+    }
+
+    #[test]
+    fn test_quoted_words_parse_back_unchanged() {
+        let words = ["git", "--format=%H %s", "src/*.rs", "it's", "", "$HOME;rm -rf ~"];
+        let line = words.iter().map(|word| quote(word)).collect::<Vec<_>>().join(" ");
+        assert!(line.starts_with("git '--format=%H %s' 'src/*.rs' 'it'\\''s' ''"));
+
+        let script = parse(&line).unwrap();
+        let ShellCommand::Simple(command) = &script.pipelines[0].commands[0] else { panic!("expected a simple command") };
+        assert_eq!(command.words, words);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("echo 'unterminated").is_err());
+        assert!(parse("echo $(date").is_err());
+        assert!(parse("| grep x").is_err());
+        assert!(parse("cargo build &&").is_err());
+        assert!(parse(":(){ :|:& };:").is_err());
+        assert!(parse("case $x in a) echo a;; esac").is_err());
+        assert!(parse("").unwrap().is_empty());
+    }
+}
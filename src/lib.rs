@@ -4,6 +4,7 @@ pub mod llm;
 pub mod tui;
 pub mod workflows;
 pub mod gui;
+pub mod cli;
 
 // Re-exports for convenience
 pub use orchestrator::{Orchestrator, OrchestratorConfig};
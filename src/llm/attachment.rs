@@ -0,0 +1,181 @@
+use anyhow::{anyhow, bail, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use tokio::fs;
+
+use super::context;
+
+/// Rough token cost of one image; providers scale images to about a megapixel
+pub const IMAGE_TOKENS: u32 = 1_600;
+
+/// Providers reject larger images
+pub const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Model families that accept image input; other models get their images dropped
+const IMAGE_MODEL_PARTS: &[&str] = &[
+    "claude-3", "claude-sonnet", "claude-opus", "claude-haiku", "gpt-4o", "gpt-4.1", "gpt-4-turbo", "gpt-5",
+    "gemini", "pixtral", "llava", "vision", "-vl",
+];
+
+/// Whether `model` accepts images, judged by its family
+pub fn model_accepts_images(model: &str) -> bool {
+    let model = model.to_lowercase();
+    IMAGE_MODEL_PARTS.iter().any(|part| model.contains(part))
+}
+
+/// Non-text content attached to a message, sent after the message text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Attachment {
+    /// Base64-encoded image
+    Image {
+        media_type: String,
+        data: String,
+    },
+    /// Excerpt of a text file. Line numbers are 1-based and inclusive.
+    File {
+        path: String,
+        start_line: Option<usize>,
+        end_line: Option<usize>,
+        content: String,
+    },
+}
+
+impl Attachment {
+    pub fn image_bytes(media_type: impl Into<String>, bytes: &[u8]) -> Self {
+        Self::Image {
+            media_type: media_type.into(),
+            data: base64::engine::general_purpose::STANDARD.encode(bytes),
+        }
+    }
+
+    pub fn image_base64(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        Self::Image {
+            media_type: media_type.into(),
+            data: data.into(),
+        }
+    }
+
+    /// Load an image file, taking the media type from its extension
+    pub async fn image_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let media_type = image_media_type(path)
+            .ok_or_else(|| anyhow!("Unsupported image type: {}", path.display()))?;
+
+        let size = fs::metadata(path).await?.len();
+        if size > MAX_IMAGE_BYTES {
+            bail!("Image {} is {} bytes, larger than the {} byte limit", path.display(), size, MAX_IMAGE_BYTES);
+        }
+
+        Ok(Self::image_bytes(media_type, &fs::read(path).await?))
+    }
+
+    /// Attach a text file, or only the given line range of it
+    pub async fn file_excerpt(path: impl AsRef<Path>, lines: Option<RangeInclusive<usize>>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).await?;
+
+        let content = match &lines {
+            Some(range) => {
+                if *range.start() == 0 || range.start() > range.end() {
+                    bail!("Invalid line range {}-{} for {}", range.start(), range.end(), path.display());
+                }
+                source.lines()
+                    .skip(range.start() - 1)
+                    .take(range.end() - range.start() + 1)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            None => source,
+        };
+
+        Ok(Self::File {
+            path: path.display().to_string(),
+            start_line: lines.as_ref().map(|r| *r.start()),
+            end_line: lines.as_ref().map(|r| *r.end()),
+            content,
+        })
+    }
+
+    /// Text sent for attachments that providers receive as text blocks
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            Self::Image { .. } => None,
+            Self::File { path, start_line, end_line, content } => {
+                let location = match (start_line, end_line) {
+                    (Some(start), Some(end)) => format!("{} (lines {}-{})", path, start, end),
+                    _ => path.clone(),
+                };
+                Some(format!("File: {}\n```\n{}\n```", location, content))
+            }
+        }
+    }
+
+    pub fn estimated_tokens(&self, model: &str) -> u32 {
+        match self {
+            Self::Image { .. } => IMAGE_TOKENS,
+            Self::File { .. } => context::estimate_tokens(model, &self.as_text().unwrap_or_default()),
+        }
+    }
+}
+
+/// Media type for image extensions accepted by both Anthropic and OpenAI-style APIs
+pub fn image_media_type(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_image_bytes_are_base64_encoded() {
+        let image = Attachment::image_bytes("image/png", b"\x89PNG");
+        assert_eq!(image, Attachment::image_base64("image/png", "iVBORw=="));
+        assert_eq!(image.estimated_tokens("claude"), IMAGE_TOKENS);
+        assert!(image.as_text().is_none());
+
+        assert_eq!(image_media_type(Path::new("diagram.JPG")), Some("image/jpeg"));
+        assert_eq!(image_media_type(Path::new("notes.txt")), None);
+    }
+
+    #[tokio::test]
+    async fn test_file_excerpt_line_range() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("lib.rs");
+        fs::write(&path, "one\ntwo\nthree\nfour\n").await.unwrap();
+
+        let excerpt = Attachment::file_excerpt(&path, Some(2..=3)).await.unwrap();
+        let text = excerpt.as_text().unwrap();
+        assert!(text.contains("(lines 2-3)"));
+        assert!(text.contains("```\ntwo\nthree\n```"));
+
+        let whole = Attachment::file_excerpt(&path, None).await.unwrap();
+        assert!(whole.as_text().unwrap().contains("four"));
+        assert!(Attachment::file_excerpt(&path, Some(0..=1)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_image_file_checks_type() {
+        let temp_dir = TempDir::new().unwrap();
+        let png = temp_dir.path().join("screen.png");
+        fs::write(&png, b"\x89PNG").await.unwrap();
+        let svg = temp_dir.path().join("diagram.svg");
+        fs::write(&svg, "<svg/>").await.unwrap();
+
+        match Attachment::image_file(&png).await.unwrap() {
+            Attachment::Image { media_type, .. } => assert_eq!(media_type, "image/png"),
+            other => panic!("unexpected attachment {:?}", other),
+        }
+        assert!(Attachment::image_file(&svg).await.is_err());
+    }
+}
@@ -0,0 +1,356 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use tokio::fs;
+
+use super::router::RouteLog;
+use super::TaskType;
+
+/// Spend cap for a single scope. `None` means unlimited.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetLimit {
+    pub max_tokens: Option<u64>,
+    pub max_cost_cents: Option<u64>,
+}
+
+/// Budget policies enforced by the router before dispatching a request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    /// Cap for all requests made during the current UTC day
+    #[serde(default)]
+    pub daily: BudgetLimit,
+    /// Cap for all requests made by this router instance
+    #[serde(default)]
+    pub session: BudgetLimit,
+    /// Daily cap for each task type
+    #[serde(default)]
+    pub per_task_type: HashMap<TaskType, BudgetLimit>,
+    /// Fraction of a limit after which requests switch to the route's budget model
+    #[serde(default = "default_downgrade_threshold")]
+    pub downgrade_threshold: f32,
+}
+
+fn default_downgrade_threshold() -> f32 {
+    0.8
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            daily: BudgetLimit::default(),
+            session: BudgetLimit::default(),
+            per_task_type: HashMap::new(),
+            downgrade_threshold: default_downgrade_threshold(),
+        }
+    }
+}
+
+/// Which budget a limit belongs to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum BudgetScope {
+    Daily,
+    Session,
+    TaskType(TaskType),
+}
+
+impl std::fmt::Display for BudgetScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetScope::Daily => write!(f, "daily"),
+            BudgetScope::Session => write!(f, "session"),
+            BudgetScope::TaskType(task_type) => write!(f, "task type {:?}", task_type),
+        }
+    }
+}
+
+/// Unit a limit is expressed in
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum BudgetUnit {
+    Tokens,
+    Cents,
+}
+
+impl BudgetUnit {
+    /// Amount for messages: whole tokens, cents to two decimals
+    pub fn format_amount(&self, amount: f64) -> String {
+        match self {
+            BudgetUnit::Tokens => format!("{:.0}", amount),
+            BudgetUnit::Cents => format!("{:.2}", amount),
+        }
+    }
+}
+
+impl std::fmt::Display for BudgetUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetUnit::Tokens => write!(f, "tokens"),
+            BudgetUnit::Cents => write!(f, "cents"),
+        }
+    }
+}
+
+/// Outcome of checking a request against the configured budgets
+#[derive(Debug, Clone, PartialEq)]
+pub enum BudgetDecision {
+    Allow,
+    Downgrade,
+    Deny {
+        scope: BudgetScope,
+        unit: BudgetUnit,
+        spent: f64,
+        limit: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Spend {
+    pub tokens: u64,
+    /// Fractional cents, so many cheap requests still add up
+    pub cost_cents: f64,
+}
+
+impl Spend {
+    fn add(&mut self, tokens: u64, cost_cents: f64) {
+        self.tokens += tokens;
+        self.cost_cents += cost_cents;
+    }
+}
+
+/// Running spend totals, seeded from the routing log so daily budgets survive restarts
+#[derive(Debug, Clone)]
+pub struct SpendTracker {
+    day: NaiveDate,
+    daily: Spend,
+    session: Spend,
+    per_task_type: HashMap<TaskType, Spend>,
+}
+
+impl Default for SpendTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpendTracker {
+    pub fn new() -> Self {
+        Self {
+            day: Utc::now().date_naive(),
+            daily: Spend::default(),
+            session: Spend::default(),
+            per_task_type: HashMap::new(),
+        }
+    }
+
+    /// Rebuild today's totals from an existing routing log. Missing files yield an empty tracker.
+    pub async fn load_from_log(log_file_path: impl AsRef<Path>) -> Result<Self> {
+        let mut tracker = Self::new();
+
+        let content = match fs::read_to_string(log_file_path.as_ref()).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(tracker),
+            Err(e) => return Err(e.into()),
+        };
+
+        for line in content.lines().filter(|l| !l.trim().is_empty()) {
+            match serde_json::from_str::<RouteLog>(line) {
+                Ok(entry) if entry.timestamp.date_naive() == tracker.day => {
+                    let spend = tracker.per_task_type.entry(entry.task_type).or_default();
+                    spend.add(entry.tokens_used as u64, entry.cost_cents.unwrap_or(0.0));
+                    tracker.daily.add(entry.tokens_used as u64, entry.cost_cents.unwrap_or(0.0));
+                }
+                Ok(_) => {}
+                Err(e) => log::debug!("Skipping unreadable routing log line: {}", e),
+            }
+        }
+
+        Ok(tracker)
+    }
+
+    /// Add the spend of a completed request
+    pub fn record(&mut self, task_type: &TaskType, tokens: u32, cost_cents: Option<f64>, at: DateTime<Utc>) {
+        self.roll_over(at.date_naive());
+
+        let tokens = tokens as u64;
+        let cost_cents = cost_cents.unwrap_or(0.0);
+
+        self.daily.add(tokens, cost_cents);
+        self.session.add(tokens, cost_cents);
+        self.per_task_type.entry(task_type.clone()).or_default().add(tokens, cost_cents);
+    }
+
+    /// Check whether a new request of the given task type may be dispatched
+    pub fn check(&mut self, config: &BudgetConfig, task_type: &TaskType) -> BudgetDecision {
+        self.roll_over(Utc::now().date_naive());
+
+        let task_spend = self.per_task_type.get(task_type).copied().unwrap_or_default();
+        let mut scopes = vec![
+            (BudgetScope::Daily, &config.daily, self.daily),
+            (BudgetScope::Session, &config.session, self.session),
+        ];
+        if let Some(limit) = config.per_task_type.get(task_type) {
+            scopes.push((BudgetScope::TaskType(task_type.clone()), limit, task_spend));
+        }
+
+        let mut decision = BudgetDecision::Allow;
+        for (scope, limit, spend) in scopes {
+            let checks = [
+                (BudgetUnit::Tokens, limit.max_tokens, spend.tokens as f64),
+                (BudgetUnit::Cents, limit.max_cost_cents, spend.cost_cents),
+            ];
+
+            for (unit, max, spent) in checks {
+                let Some(max) = max.map(|max| max as f64) else { continue };
+
+                if spent >= max {
+                    return BudgetDecision::Deny { scope, unit, spent, limit: max };
+                }
+
+                if spent as f32 >= max as f32 * config.downgrade_threshold {
+                    decision = BudgetDecision::Downgrade;
+                }
+            }
+        }
+
+        decision
+    }
+
+    pub fn daily_spend(&self) -> Spend {
+        self.daily
+    }
+
+    pub fn session_spend(&self) -> Spend {
+        self.session
+    }
+
+    pub fn task_type_spend(&self, task_type: &TaskType) -> Spend {
+        self.per_task_type.get(task_type).copied().unwrap_or_default()
+    }
+
+    fn roll_over(&mut self, today: NaiveDate) {
+        if today != self.day {
+            self.day = today;
+            self.daily = Spend::default();
+            self.per_task_type.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::Provider;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    fn log_entry(task_type: TaskType, tokens: u32, cost_cents: f64, timestamp: DateTime<Utc>) -> RouteLog {
+        RouteLog {
+            timestamp,
+            request_id: Uuid::new_v4(),
+            task_type,
+            attempted_provider: Provider::Claude,
+            final_provider: Provider::Claude,
+            success: true,
+            duration_ms: 100,
+            error_message: None,
+            retry_count: 0,
+            cost_cents: Some(cost_cents),
+            tokens_used: tokens,
+            model: None,
+            cache_hit: false,
+            prompt_template: None,
+            redactions: Default::default(),
+            race_winner: None,
+            purpose: Default::default(),
+            candidate_index: None,
+        }
+    }
+
+    #[test]
+    fn test_unlimited_budget_allows() {
+        let mut tracker = SpendTracker::new();
+        tracker.record(&TaskType::Plan, 1_000_000, Some(10_000.0), Utc::now());
+
+        let decision = tracker.check(&BudgetConfig::default(), &TaskType::Plan);
+        assert_eq!(decision, BudgetDecision::Allow);
+    }
+
+    #[test]
+    fn test_downgrade_and_deny_thresholds() {
+        let mut config = BudgetConfig::default();
+        config.daily.max_tokens = Some(1000);
+
+        let mut tracker = SpendTracker::new();
+        tracker.record(&TaskType::Review, 500, None, Utc::now());
+        assert_eq!(tracker.check(&config, &TaskType::Review), BudgetDecision::Allow);
+
+        tracker.record(&TaskType::Review, 300, None, Utc::now());
+        assert_eq!(tracker.check(&config, &TaskType::Review), BudgetDecision::Downgrade);
+
+        tracker.record(&TaskType::Review, 200, None, Utc::now());
+        assert_eq!(
+            tracker.check(&config, &TaskType::Review),
+            BudgetDecision::Deny { scope: BudgetScope::Daily, unit: BudgetUnit::Tokens, spent: 1000.0, limit: 1000.0 }
+        );
+    }
+
+    #[test]
+    fn test_per_task_type_limit_is_isolated() {
+        let mut config = BudgetConfig::default();
+        config.per_task_type.insert(TaskType::Plan, BudgetLimit { max_tokens: None, max_cost_cents: Some(50) });
+
+        let mut tracker = SpendTracker::new();
+        tracker.record(&TaskType::Plan, 100, Some(60.0), Utc::now());
+
+        assert!(matches!(tracker.check(&config, &TaskType::Plan), BudgetDecision::Deny { .. }));
+        assert_eq!(tracker.check(&config, &TaskType::Status), BudgetDecision::Allow);
+    }
+
+    #[test]
+    fn test_sub_cent_costs_accumulate() {
+        let config = BudgetConfig {
+            daily: BudgetLimit { max_tokens: None, max_cost_cents: Some(1) },
+            ..Default::default()
+        };
+
+        let mut tracker = SpendTracker::new();
+        for _ in 0..3 {
+            tracker.record(&TaskType::Review, 10, Some(0.4), Utc::now());
+        }
+
+        match tracker.check(&config, &TaskType::Review) {
+            BudgetDecision::Deny { unit: BudgetUnit::Cents, spent, .. } => assert!((spent - 1.2).abs() < 1e-9),
+            other => panic!("expected a cents denial, got {:?}", other),
+        }
+        let error = crate::llm::LlmError::BudgetExceeded { scope: BudgetScope::Daily, unit: BudgetUnit::Cents, spent: 1.2, limit: 1.0 };
+        assert_eq!(error.to_string(), "Budget exceeded for daily: spent 1.20 of 1.00 cents");
+    }
+
+    #[tokio::test]
+    async fn test_load_from_log_counts_only_today() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("log.jsonl");
+
+        let today = log_entry(TaskType::Plan, 400, 12.5, Utc::now());
+        let yesterday = log_entry(TaskType::Plan, 900, 30.0, Utc::now() - chrono::Duration::days(1));
+        let content = format!(
+            "{}\n{}\nnot json\n",
+            serde_json::to_string(&today).unwrap(),
+            serde_json::to_string(&yesterday).unwrap()
+        );
+        fs::write(&log_path, content).await.unwrap();
+
+        let tracker = SpendTracker::load_from_log(&log_path).await.unwrap();
+        assert_eq!(tracker.daily_spend(), Spend { tokens: 400, cost_cents: 12.5 });
+        assert_eq!(tracker.task_type_spend(&TaskType::Plan).tokens, 400);
+        assert_eq!(tracker.session_spend(), Spend::default());
+    }
+
+    #[tokio::test]
+    async fn test_load_from_missing_log() {
+        let temp_dir = TempDir::new().unwrap();
+        let tracker = SpendTracker::load_from_log(temp_dir.path().join("missing.jsonl")).await.unwrap();
+        assert_eq!(tracker.daily_spend(), Spend::default());
+    }
+}
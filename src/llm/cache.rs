@@ -0,0 +1,190 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use tokio::fs;
+
+use super::{Attachment, LlmRequest, LlmResponse, MessageRole, Provider, TaskType};
+
+/// Which requests are cached and for how long
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// Task types whose responses may be served from the cache
+    #[serde(default)]
+    pub task_types: Vec<TaskType>,
+    /// Entries older than this are treated as misses and removed
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+fn default_ttl_secs() -> u64 {
+    24 * 60 * 60
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            task_types: Vec::new(),
+            ttl_secs: default_ttl_secs(),
+        }
+    }
+}
+
+/// Fields that determine whether two requests would produce the same response
+#[derive(Serialize)]
+struct CacheKey<'a> {
+    provider: &'a Provider,
+    model: &'a str,
+    messages: Vec<(&'a MessageRole, String, &'a [Attachment])>,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    created_at: DateTime<Utc>,
+    response: LlmResponse,
+}
+
+/// Content-addressed response cache stored as one JSON file per key
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    config: CacheConfig,
+}
+
+impl ResponseCache {
+    pub fn new(dir: impl Into<PathBuf>, config: CacheConfig) -> Self {
+        Self {
+            dir: dir.into(),
+            config,
+        }
+    }
+
+    pub fn is_enabled_for(&self, task_type: &TaskType) -> bool {
+        self.config.task_types.contains(task_type)
+    }
+
+    /// Hash the request as it will be sent to `provider` using `model`
+    pub fn key(provider: &Provider, model: &str, request: &LlmRequest) -> String {
+        let key = CacheKey {
+            provider,
+            model,
+            messages: request.messages.iter()
+                .map(|m| (&m.role, normalize(&m.content), m.attachments.as_slice()))
+                .collect(),
+            temperature: request.temperature,
+            max_tokens: request.max_tokens,
+        };
+
+        let encoded = serde_json::to_vec(&key).expect("cache key is always serializable");
+        hex::encode(Sha256::digest(&encoded))
+    }
+
+    /// Fetch an unexpired response. Expired or unreadable entries are removed.
+    pub async fn get(&self, key: &str) -> Option<LlmResponse> {
+        let path = self.entry_path(key);
+        let content = fs::read_to_string(&path).await.ok()?;
+
+        match serde_json::from_str::<CacheEntry>(&content) {
+            Ok(entry) if Utc::now() - entry.created_at < Duration::seconds(self.config.ttl_secs as i64) => {
+                Some(entry.response)
+            }
+            Ok(_) => {
+                let _ = fs::remove_file(&path).await;
+                None
+            }
+            Err(e) => {
+                log::debug!("Discarding unreadable cache entry {}: {}", key, e);
+                let _ = fs::remove_file(&path).await;
+                None
+            }
+        }
+    }
+
+    pub async fn put(&self, key: &str, response: &LlmResponse) -> Result<()> {
+        fs::create_dir_all(&self.dir).await?;
+
+        let entry = CacheEntry {
+            created_at: Utc::now(),
+            response: response.clone(),
+        };
+        fs::write(self.entry_path(key), serde_json::to_string(&entry)?).await?;
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+/// Line-ending and trailing-whitespace differences should not defeat the cache
+fn normalize(content: &str) -> String {
+    content
+        .replace("\r\n", "\n")
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{Message, Usage};
+    use tempfile::TempDir;
+
+    fn response(request: &LlmRequest) -> LlmResponse {
+        LlmResponse {
+            id: request.id,
+            provider: Provider::Claude,
+            model: "claude-3-5-sonnet-20241022".to_string(),
+            content: "cached".to_string(),
+            usage: Usage { prompt_tokens: 10, completion_tokens: 5, total_tokens: 15, ..Default::default() },
+            duration_ms: 100,
+            cost_cents: Some(1.0),
+            rate_limit: None,
+            cache_hit: false,
+        }
+    }
+
+    #[test]
+    fn test_key_normalizes_whitespace_but_not_content() {
+        let a = LlmRequest::new(TaskType::Review, vec![Message::user("diff\r\nline  \n".to_string())]);
+        let b = LlmRequest::new(TaskType::Review, vec![Message::user("diff\nline".to_string())]);
+        let c = LlmRequest::new(TaskType::Review, vec![Message::user("diff\nother".to_string())]);
+
+        let key = |r: &LlmRequest| ResponseCache::key(&Provider::Claude, "sonnet", r);
+        assert_eq!(key(&a), key(&b));
+        assert_ne!(key(&a), key(&c));
+        assert_ne!(key(&a), ResponseCache::key(&Provider::Claude, "haiku", &a));
+        assert_ne!(key(&a), key(&a.clone().with_temperature(0.5)));
+        
+        let with_image = LlmRequest::new(TaskType::Review, vec![
+            Message::user("diff\nline".to_string()).with_attachment(crate::llm::Attachment::image_bytes("image/png", b"png")),
+        ]);
+        assert_ne!(key(&b), key(&with_image));
+    }
+
+    #[tokio::test]
+    async fn test_put_get_and_expiry() {
+        let temp_dir = TempDir::new().unwrap();
+        let request = LlmRequest::new(TaskType::Status, vec![Message::user("status".to_string())]);
+        let key = ResponseCache::key(&Provider::Claude, "sonnet", &request);
+
+        let cache = ResponseCache::new(temp_dir.path(), CacheConfig { task_types: vec![TaskType::Status], ttl_secs: 60 });
+        assert!(cache.is_enabled_for(&TaskType::Status));
+        assert!(!cache.is_enabled_for(&TaskType::Plan));
+        assert!(cache.get(&key).await.is_none());
+
+        cache.put(&key, &response(&request)).await.unwrap();
+        assert_eq!(cache.get(&key).await.unwrap().content, "cached");
+
+        let expired = ResponseCache::new(temp_dir.path(), CacheConfig { task_types: vec![TaskType::Status], ttl_secs: 0 });
+        assert!(expired.get(&key).await.is_none());
+        assert!(!temp_dir.path().join(format!("{}.json", key)).exists());
+    }
+}
@@ -6,7 +6,7 @@ use tokio::time::Duration;
 
 use super::{
     LlmProvider, LlmRequest, LlmResponse, Message, MessageRole, Provider, 
-    ProviderConfig, Usage, LlmError
+    ProviderConfig, Usage, LlmError, RateLimitInfo, Attachment
 };
 
 #[derive(Debug)]
@@ -20,13 +20,88 @@ struct ClaudeApiRequest {
     model: String,
     max_tokens: u32,
     temperature: f32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    system: Vec<ClaudeContentBlock>,
     messages: Vec<ClaudeMessage>,
 }
 
 #[derive(Debug, Serialize)]
 struct ClaudeMessage {
     role: String,
-    content: String,
+    content: ClaudeMessageContent,
+}
+
+/// Plain text, or content blocks when the message has attachments or a cache breakpoint
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(untagged)]
+enum ClaudeMessageContent {
+    Text(String),
+    Blocks(Vec<ClaudeContentBlock>),
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClaudeContentBlock {
+    Text {
+        text: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<ClaudeCacheControl>,
+    },
+    Image {
+        source: ClaudeImageSource,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<ClaudeCacheControl>,
+    },
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct ClaudeImageSource {
+    #[serde(rename = "type")]
+    source_type: &'static str,
+    media_type: String,
+    data: String,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct ClaudeCacheControl {
+    #[serde(rename = "type")]
+    control_type: &'static str,
+}
+
+impl ClaudeContentBlock {
+    fn text(text: String) -> Self {
+        Self::Text { text, cache_control: None }
+    }
+    
+    fn from_attachment(attachment: &Attachment) -> Self {
+        match attachment {
+            Attachment::Image { media_type, data } => Self::Image {
+                source: ClaudeImageSource {
+                    source_type: "base64",
+                    media_type: media_type.clone(),
+                    data: data.clone(),
+                },
+                cache_control: None,
+            },
+            Attachment::File { .. } => Self::text(attachment.as_text().unwrap_or_default()),
+        }
+    }
+    
+    /// Message text followed by its attachments; a cache breakpoint goes on the last block
+    fn from_message(msg: &Message) -> Vec<Self> {
+        let mut blocks = Vec::new();
+        if !msg.content.is_empty() || msg.attachments.is_empty() {
+            blocks.push(Self::text(msg.content.clone()));
+        }
+        blocks.extend(msg.attachments.iter().map(Self::from_attachment));
+        
+        if msg.cache_breakpoint {
+            if let Some(Self::Text { cache_control, .. } | Self::Image { cache_control, .. }) = blocks.last_mut() {
+                *cache_control = Some(ClaudeCacheControl { control_type: "ephemeral" });
+            }
+        }
+        blocks
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -39,8 +114,13 @@ struct ClaudeApiResponse {
 
 #[derive(Debug, Deserialize)]
 struct ClaudeUsage {
+    /// Uncached input tokens only; cache writes and reads are reported separately
     input_tokens: u32,
     output_tokens: u32,
+    #[serde(default)]
+    cache_creation_input_tokens: u32,
+    #[serde(default)]
+    cache_read_input_tokens: u32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -73,17 +153,39 @@ impl ClaudeClient {
     }
     
     fn build_request(&self, request: &LlmRequest) -> ClaudeApiRequest {
-        let messages = request.messages.iter().map(|msg| {
-            ClaudeMessage {
-                role: self.map_message_role(&msg.role),
-                content: msg.content.clone(),
-            }
-        }).collect();
+        // System messages go into the top-level system blocks so they can carry cache breakpoints
+        let system = request.messages.iter()
+            .filter(|msg| msg.role == MessageRole::System)
+            .flat_map(ClaudeContentBlock::from_message)
+            .filter(|block| {
+                let is_text = matches!(block, ClaudeContentBlock::Text { .. });
+                if !is_text {
+                    log::warn!("Dropping image attached to a system message; Claude only accepts text there");
+                }
+                is_text
+            })
+            .collect();
+        
+        let messages = request.messages.iter()
+            .filter(|msg| msg.role != MessageRole::System)
+            .map(|msg| {
+                let content = if msg.cache_breakpoint || !msg.attachments.is_empty() {
+                    ClaudeMessageContent::Blocks(ClaudeContentBlock::from_message(msg))
+                } else {
+                    ClaudeMessageContent::Text(msg.content.clone())
+                };
+                ClaudeMessage {
+                    role: self.map_message_role(&msg.role),
+                    content,
+                }
+            })
+            .collect();
         
         ClaudeApiRequest {
-            model: self.config.model.clone(),
+            model: request.model.clone().unwrap_or_else(|| self.config.model.clone()),
             max_tokens: request.max_tokens.unwrap_or(self.config.max_tokens),
             temperature: request.temperature.unwrap_or(0.7),
+            system,
             messages,
         }
     }
@@ -96,13 +198,13 @@ impl ClaudeClient {
         }
     }
     
-    async fn make_api_request(&self, claude_request: &ClaudeApiRequest) -> Result<ClaudeApiResponse> {
+    async fn make_api_request(&self, claude_request: &ClaudeApiRequest) -> Result<(ClaudeApiResponse, RateLimitInfo)> {
         let url = format!("{}/messages", self.config.base_url);
         
         let response = self.client
             .post(&url)
             .header("Content-Type", "application/json")
-            .header("x-api-key", &self.config.api_key)
+            .header("x-api-key", self.config.api_key.expose())
             .header("anthropic-version", "2023-06-01")
             .json(claude_request)
             .send()
@@ -110,17 +212,21 @@ impl ClaudeClient {
             .map_err(|e| anyhow!("HTTP request failed: {}", e))?;
         
         let status = response.status();
+        let rate_limit = RateLimitInfo::from_anthropic_headers(response.headers());
         let response_text = response.text().await
             .map_err(|e| anyhow!("Failed to read response: {}", e))?;
         
         if status.is_success() {
             serde_json::from_str::<ClaudeApiResponse>(&response_text)
+                .map(|parsed| (parsed, rate_limit))
                 .map_err(|e| anyhow!("Failed to parse Claude response: {}", e))
+        } else if status.as_u16() == 429 {
+            Err(LlmError::RateLimited { provider: Provider::Claude, info: rate_limit }.into())
         } else {
             // Try to parse as error response
             if let Ok(error_response) = serde_json::from_str::<ClaudeErrorResponse>(&response_text) {
                 match error_response.error.error_type.as_str() {
-                    "rate_limit_error" => Err(LlmError::RateLimited { provider: Provider::Claude }.into()),
+                    "rate_limit_error" => Err(LlmError::RateLimited { provider: Provider::Claude, info: rate_limit }.into()),
                     _ => Err(LlmError::RequestFailed { 
                         message: format!("Claude API error: {}", error_response.error.message) 
                     }.into()),
@@ -131,6 +237,33 @@ impl ClaudeClient {
         }
     }
     
+    /// Cache writes cost 25% more than regular input tokens
+    const CACHE_WRITE_MULTIPLIER: f64 = 1.25;
+    /// Cache reads cost 10% of regular input tokens
+    const CACHE_READ_MULTIPLIER: f64 = 0.1;
+    
+    /// Per-token (input, output) price in cents for the given model
+    fn pricing_cents_per_token(model: &str) -> (f64, f64) {
+        if model.contains("haiku") {
+            // Claude 3.5 Haiku: $0.80/1M input, $4/1M output
+            (0.00008, 0.0004)
+        } else if model.contains("opus") {
+            // Claude 3 Opus: $15/1M input, $75/1M output
+            (0.0015, 0.0075)
+        } else {
+            // Claude 3.5 Sonnet: $3/1M input, $15/1M output
+            (0.0003, 0.0015)
+        }
+    }
+    
+    fn cost_cents(model: &str, usage: &ClaudeUsage) -> f64 {
+        let (input_cents, output_cents) = Self::pricing_cents_per_token(model);
+        usage.input_tokens as f64 * input_cents
+            + usage.cache_creation_input_tokens as f64 * input_cents * Self::CACHE_WRITE_MULTIPLIER
+            + usage.cache_read_input_tokens as f64 * input_cents * Self::CACHE_READ_MULTIPLIER
+            + usage.output_tokens as f64 * output_cents
+    }
+    
     fn parse_response(&self, request_id: uuid::Uuid, claude_response: ClaudeApiResponse, duration_ms: u64) -> LlmResponse {
         let content = claude_response.content
             .into_iter()
@@ -139,17 +272,19 @@ impl ClaudeClient {
             .collect::<Vec<_>>()
             .join("\n");
         
+        let claude_usage = &claude_response.usage;
+        let prompt_tokens = claude_usage.input_tokens
+            + claude_usage.cache_creation_input_tokens
+            + claude_usage.cache_read_input_tokens;
         let usage = Usage {
-            prompt_tokens: claude_response.usage.input_tokens,
-            completion_tokens: claude_response.usage.output_tokens,
-            total_tokens: claude_response.usage.input_tokens + claude_response.usage.output_tokens,
+            prompt_tokens,
+            completion_tokens: claude_usage.output_tokens,
+            total_tokens: prompt_tokens + claude_usage.output_tokens,
+            cache_creation_input_tokens: claude_usage.cache_creation_input_tokens,
+            cache_read_input_tokens: claude_usage.cache_read_input_tokens,
         };
         
-        // Estimate cost based on Claude 3.5 Sonnet pricing
-        // Input: $3/1M tokens, Output: $15/1M tokens
-        let cost_cents = Some(
-            (usage.prompt_tokens as f64 * 0.0003 + usage.completion_tokens as f64 * 0.0015) as u32
-        );
+        let cost_cents = Some(Self::cost_cents(&claude_response.model, claude_usage));
         
         LlmResponse {
             id: request_id,
@@ -159,6 +294,8 @@ impl ClaudeClient {
             usage,
             duration_ms,
             cost_cents,
+            rate_limit: None,
+            cache_hit: false,
         }
     }
 }
@@ -169,10 +306,11 @@ impl LlmProvider for ClaudeClient {
         let start_time = Instant::now();
         
         let claude_request = self.build_request(request);
-        let claude_response = self.make_api_request(&claude_request).await?;
+        let (claude_response, rate_limit) = self.make_api_request(&claude_request).await?;
         
         let duration_ms = start_time.elapsed().as_millis() as u64;
-        let response = self.parse_response(request.id, claude_response, duration_ms);
+        let mut response = self.parse_response(request.id, claude_response, duration_ms);
+        response.rate_limit = (!rate_limit.is_empty()).then_some(rate_limit);
         
         Ok(response)
     }
@@ -182,7 +320,7 @@ impl LlmProvider for ClaudeClient {
     }
     
     fn is_available(&self) -> bool {
-        !self.config.api_key.is_empty() && !self.config.api_key.starts_with('$')
+        !self.config.api_key.is_empty() && !self.config.api_key.expose().starts_with('$')
     }
 }
 
@@ -194,11 +332,17 @@ mod tests {
     #[test]
     fn test_claude_client_creation() {
         let config = ProviderConfig {
-            api_key: "test-key".to_string(),
+            api_key: "test-key".into(),
+            credential: None,
             base_url: "https://api.anthropic.com/v1".to_string(),
             model: "claude-3-5-sonnet-20241022".to_string(),
             max_tokens: 4096,
             timeout_ms: 30000,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            context_window: None,
+            embedding_model: None,
+            accepts_images: None,
         };
         
         let client = ClaudeClient::new(config);
@@ -209,11 +353,17 @@ mod tests {
     #[test]
     fn test_message_role_mapping() {
         let config = ProviderConfig {
-            api_key: "test-key".to_string(),
+            api_key: "test-key".into(),
+            credential: None,
             base_url: "https://api.anthropic.com/v1".to_string(),
             model: "claude-3-5-sonnet-20241022".to_string(),
             max_tokens: 4096,
             timeout_ms: 30000,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            context_window: None,
+            embedding_model: None,
+            accepts_images: None,
         };
         
         let client = ClaudeClient::new(config);
@@ -226,11 +376,17 @@ mod tests {
     #[test]
     fn test_build_request() {
         let config = ProviderConfig {
-            api_key: "test-key".to_string(),
+            api_key: "test-key".into(),
+            credential: None,
             base_url: "https://api.anthropic.com/v1".to_string(),
             model: "claude-3-5-sonnet-20241022".to_string(),
             max_tokens: 4096,
             timeout_ms: 30000,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            context_window: None,
+            embedding_model: None,
+            accepts_images: None,
         };
         
         let client = ClaudeClient::new(config);
@@ -251,30 +407,127 @@ mod tests {
         assert_eq!(claude_request.temperature, 0.3);
         assert_eq!(claude_request.messages.len(), 2);
         assert_eq!(claude_request.messages[0].role, "user");
-        assert_eq!(claude_request.messages[0].content, "Hello");
+        assert_eq!(claude_request.messages[0].content, ClaudeMessageContent::Text("Hello".to_string()));
     }
     
     #[test]
     fn test_availability_check() {
         let mut config = ProviderConfig {
-            api_key: "test-key".to_string(),
+            api_key: "test-key".into(),
+            credential: None,
             base_url: "https://api.anthropic.com/v1".to_string(),
             model: "claude-3-5-sonnet-20241022".to_string(),
             max_tokens: 4096,
             timeout_ms: 30000,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            context_window: None,
+            embedding_model: None,
+            accepts_images: None,
         };
         
         let client = ClaudeClient::new(config.clone());
         assert!(client.is_available());
         
         // Test with empty API key
-        config.api_key = "".to_string();
+        config.api_key = "".into();
         let client = ClaudeClient::new(config.clone());
         assert!(!client.is_available());
         
         // Test with environment variable placeholder
-        config.api_key = "${ANTHROPIC_API_KEY}".to_string();
+        config.api_key = "${ANTHROPIC_API_KEY}".into();
         let client = ClaudeClient::new(config);
         assert!(!client.is_available());
     }
-}
\ No newline at end of file
+    
+    fn fixture_client() -> ClaudeClient {
+        ClaudeClient::new(ProviderConfig {
+            api_key: "test-key".into(),
+            credential: None,
+            base_url: "https://api.anthropic.com/v1".to_string(),
+            model: "claude-3-5-sonnet-20241022".to_string(),
+            max_tokens: 4096,
+            timeout_ms: 30000,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            context_window: None,
+            embedding_model: None,
+            accepts_images: None,
+        })
+    }
+    
+    fn parse_fixture(source: &str) -> LlmResponse {
+        let api_response: ClaudeApiResponse = serde_json::from_str(source).unwrap();
+        fixture_client().parse_response(uuid::Uuid::new_v4(), api_response, 10)
+    }
+    
+    #[test]
+    fn test_cache_breakpoints_serialize_as_blocks() {
+        let messages = vec![
+            Message::system("Follow the project conventions.".to_string()).with_cache_breakpoint(),
+            Message::user("src/lib.rs:\npub mod llm;".to_string()).with_cache_breakpoint(),
+            Message::user("Review the diff.".to_string()),
+        ];
+        let request = LlmRequest::new(TaskType::Review, messages).with_temperature(0.5);
+        
+        let body = serde_json::to_value(fixture_client().build_request(&request)).unwrap();
+        let expected: serde_json::Value = serde_json::from_str(include_str!("../../tests/fixtures/claude/cached_request.json")).unwrap();
+        assert_eq!(body, expected);
+    }
+    
+    #[test]
+    fn test_attachments_serialize_as_content_blocks() {
+        let message = Message::user("Does the UI match the diagram?".to_string())
+            .with_attachment(Attachment::image_base64("image/png", "iVBORw=="))
+            .with_attachment(Attachment::File {
+                path: "plans/architecture.md".to_string(),
+                start_line: None,
+                end_line: None,
+                content: "Router -> Providers".to_string(),
+            })
+            .with_cache_breakpoint();
+        let request = LlmRequest::new(TaskType::Review, vec![message]);
+        
+        let body = serde_json::to_value(fixture_client().build_request(&request)).unwrap();
+        assert_eq!(body["messages"][0]["content"], serde_json::json!([
+            {"type": "text", "text": "Does the UI match the diagram?"},
+            {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw=="}},
+            {
+                "type": "text",
+                "text": "File: plans/architecture.md\n```\nRouter -> Providers\n```",
+                "cache_control": {"type": "ephemeral"},
+            },
+        ]));
+    }
+    
+    #[test]
+    fn test_plain_request_has_no_cache_fields() {
+        let request = LlmRequest::new(TaskType::Plan, vec![Message::user("Hello".to_string())]);
+        let body = serde_json::to_value(fixture_client().build_request(&request)).unwrap();
+        
+        assert!(body.get("system").is_none());
+        assert_eq!(body["messages"][0]["content"], "Hello");
+    }
+    
+    #[test]
+    fn test_cache_usage_from_fixtures() {
+        let write = parse_fixture(include_str!("../../tests/fixtures/claude/cache_write_response.json"));
+        assert_eq!(write.usage.cache_creation_input_tokens, 40_000);
+        assert_eq!(write.usage.cache_read_input_tokens, 0);
+        assert_eq!(write.usage.prompt_tokens, 40_050);
+        assert_eq!(write.usage.total_tokens, 40_550);
+        // 50 * 0.0003 + 40000 * 0.0003 * 1.25 + 500 * 0.0015 = 15.765
+        assert!((write.cost_cents.unwrap() - 15.765).abs() < 1e-9);
+        
+        let read = parse_fixture(include_str!("../../tests/fixtures/claude/cache_read_response.json"));
+        assert_eq!(read.usage.cache_read_input_tokens, 40_000);
+        assert_eq!(read.usage.prompt_tokens, 40_050);
+        // 50 * 0.0003 + 40000 * 0.0003 * 0.1 + 500 * 0.0015 = 1.965
+        assert!((read.cost_cents.unwrap() - 1.965).abs() < 1e-9);
+        
+        let plain = parse_fixture(include_str!("../../tests/fixtures/claude/uncached_response.json"));
+        assert_eq!(plain.content, "Hello!");
+        assert_eq!(plain.usage.cache_creation_input_tokens, 0);
+        assert_eq!(plain.usage.total_tokens, 18);
+    }
+}
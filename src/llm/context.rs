@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{Message, TaskType};
+
+/// Tokens added per message for role markers and separators
+const MESSAGE_OVERHEAD_TOKENS: u32 = 4;
+
+/// What the router does when a prompt does not fit a provider's context window
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ContextStrategy {
+    /// Fail with `LlmError::ContextOverflow`
+    Reject,
+    /// Cut the middle out of the largest message
+    TruncateMiddle,
+    /// Replace all but the last message with a model-written summary
+    SummarizeOlder,
+    /// Condense the largest message chunk by chunk, then send the condensed version
+    MapReduce,
+}
+
+/// Context-window handling, with per-task-type overrides
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextConfig {
+    #[serde(default = "default_strategy")]
+    pub strategy: ContextStrategy,
+    #[serde(default)]
+    pub per_task_type: HashMap<TaskType, ContextStrategy>,
+}
+
+fn default_strategy() -> ContextStrategy {
+    ContextStrategy::TruncateMiddle
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            strategy: default_strategy(),
+            per_task_type: HashMap::new(),
+        }
+    }
+}
+
+impl ContextConfig {
+    pub fn strategy_for(&self, task_type: &TaskType) -> ContextStrategy {
+        self.per_task_type.get(task_type).copied().unwrap_or(self.strategy)
+    }
+}
+
+/// Average characters per token for a model family. Claude's tokenizer is denser than GPT-style ones.
+pub fn chars_per_token(model: &str) -> f32 {
+    if model.contains("claude") {
+        3.5
+    } else {
+        4.0
+    }
+}
+
+/// Context window used when `ProviderConfig::context_window` is not set
+pub fn default_context_window(model: &str) -> u32 {
+    if model.contains("claude") {
+        200_000
+    } else {
+        128_000
+    }
+}
+
+pub fn estimate_tokens(model: &str, text: &str) -> u32 {
+    (text.chars().count() as f32 / chars_per_token(model)).ceil() as u32
+}
+
+pub fn estimate_messages(model: &str, messages: &[Message]) -> u32 {
+    messages.iter()
+        .map(|m| {
+            let attachments: u32 = m.attachments.iter().map(|a| a.estimated_tokens(model)).sum();
+            estimate_tokens(model, &m.content) + attachments + MESSAGE_OVERHEAD_TOKENS
+        })
+        .sum()
+}
+
+/// Index of the message with the most content
+pub fn largest_message(messages: &[Message]) -> Option<usize> {
+    messages.iter()
+        .enumerate()
+        .max_by_key(|(_, m)| m.content.len())
+        .map(|(i, _)| i)
+}
+
+/// Cut the middle of the largest message until the messages fit `max_tokens`.
+/// Returns false when even an emptied message would not be enough.
+pub fn truncate_middle(messages: &mut [Message], model: &str, max_tokens: u32) -> bool {
+    let estimated = estimate_messages(model, messages);
+    if estimated <= max_tokens {
+        return true;
+    }
+    let Some(index) = largest_message(messages) else { return false };
+
+    let content: Vec<char> = messages[index].content.chars().collect();
+    let excess_chars = ((estimated - max_tokens) as f32 * chars_per_token(model)).ceil() as usize;
+    // Leave room for the marker itself
+    let remove = excess_chars + 64;
+    if remove >= content.len() {
+        return false;
+    }
+
+    let keep_head = (content.len() - remove) / 2;
+    let keep_tail = content.len() - remove - keep_head;
+    let head: String = content[..keep_head].iter().collect();
+    let tail: String = content[content.len() - keep_tail..].iter().collect();
+    messages[index].content = format!("{}\n\n[... {} characters truncated ...]\n\n{}", head, remove, tail);
+
+    estimate_messages(model, messages) <= max_tokens
+}
+
+/// Split text into pieces of at most `max_tokens`, preferring line boundaries
+pub fn split_into_chunks(model: &str, text: &str, max_tokens: u32) -> Vec<String> {
+    let max_chars = ((max_tokens as f32 * chars_per_token(model)) as usize).max(1);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for line in text.split_inclusive('\n') {
+        if current.chars().count() + line.chars().count() > max_chars && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+        }
+
+        // Lines longer than a whole chunk are split hard
+        let chars: Vec<char> = line.chars().collect();
+        if chars.len() > max_chars {
+            for piece in chars.chunks(max_chars) {
+                chunks.push(piece.iter().collect());
+            }
+        } else {
+            current.push_str(line);
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimates_by_model_family() {
+        let text = "a".repeat(700);
+        assert_eq!(estimate_tokens("claude-3-5-sonnet-20241022", &text), 200);
+        assert_eq!(estimate_tokens("openai/gpt-4o", &text), 175);
+        assert_eq!(estimate_messages("gpt", &[Message::user("abcd".to_string())]), 1 + MESSAGE_OVERHEAD_TOKENS);
+        assert_eq!(default_context_window("anthropic/claude-3.5-haiku"), 200_000);
+    }
+
+    #[test]
+    fn test_truncate_middle_keeps_both_ends() {
+        let content = format!("START{}END", "x".repeat(4_000));
+        let mut messages = vec![Message::system("rules".to_string()), Message::user(content)];
+
+        assert!(truncate_middle(&mut messages, "gpt", 500));
+        assert!(estimate_messages("gpt", &messages) <= 500);
+        assert_eq!(messages[0].content, "rules");
+        assert!(messages[1].content.starts_with("START"));
+        assert!(messages[1].content.ends_with("END"));
+        assert!(messages[1].content.contains("characters truncated"));
+    }
+
+    #[test]
+    fn test_truncate_middle_gives_up_when_impossible() {
+        let mut messages = vec![Message::user("x".repeat(100)), Message::user("y".repeat(100))];
+        assert!(!truncate_middle(&mut messages, "gpt", 10));
+    }
+
+    #[test]
+    fn test_split_into_chunks() {
+        let text = "line one\nline two\nline three\n";
+        let chunks = split_into_chunks("gpt", text, 3);
+        assert_eq!(chunks, vec!["line one\n", "line two\n", "line three\n"]);
+        assert_eq!(chunks.concat(), text);
+
+        let long = "z".repeat(50);
+        assert_eq!(split_into_chunks("gpt", &long, 5).len(), 3);
+    }
+
+    #[test]
+    fn test_strategy_overrides() {
+        let mut config = ContextConfig::default();
+        config.per_task_type.insert(TaskType::Review, ContextStrategy::MapReduce);
+        assert_eq!(config.strategy_for(&TaskType::Review), ContextStrategy::MapReduce);
+        assert_eq!(config.strategy_for(&TaskType::Plan), ContextStrategy::TruncateMiddle);
+    }
+}
@@ -0,0 +1,260 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::fs;
+use uuid::Uuid;
+
+use super::context;
+use super::prompts::PromptLibrary;
+use super::{LlmRequest, LlmResponse, LlmRouter, Message, TaskType};
+
+/// File name used when a conversation is stored in a run directory
+pub const CONVERSATION_FILE: &str = "conversation.json";
+
+fn default_max_history_tokens() -> u32 {
+    8_000
+}
+
+fn default_keep_recent() -> usize {
+    4
+}
+
+/// A message together with the workflow step that produced it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationTurn {
+    pub task_type: TaskType,
+    pub message: Message,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Message history shared by PLAN, REVIEW and FOLLOWUP requests within one run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Model-written summary of turns that were folded out of `turns`
+    pub summary: Option<String>,
+    pub turns: Vec<ConversationTurn>,
+    /// History size above which older turns are summarized
+    #[serde(default = "default_max_history_tokens")]
+    pub max_history_tokens: u32,
+    /// Turns always kept verbatim when summarizing
+    #[serde(default = "default_keep_recent")]
+    pub keep_recent: usize,
+}
+
+impl Default for Conversation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Conversation {
+    pub fn new() -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            created_at: now,
+            updated_at: now,
+            summary: None,
+            turns: Vec::new(),
+            max_history_tokens: default_max_history_tokens(),
+            keep_recent: default_keep_recent(),
+        }
+    }
+
+    pub fn push(&mut self, task_type: TaskType, message: Message) {
+        self.updated_at = Utc::now();
+        self.turns.push(ConversationTurn {
+            task_type,
+            message,
+            timestamp: self.updated_at,
+        });
+    }
+
+    /// Record a completed exchange, e.g. a workflow's request and the model's answer
+    pub fn record(&mut self, task_type: TaskType, user: impl Into<String>, assistant: impl Into<String>) {
+        self.push(task_type.clone(), Message::user(user.into()));
+        self.push(task_type, Message::assistant(assistant.into()));
+    }
+
+    /// History as sent to a provider, with the running summary first
+    pub fn messages(&self) -> Vec<Message> {
+        let summary = self.summary.iter()
+            .map(|s| Message::user(format!("Summary of earlier conversation:\n{}", s)));
+        summary.chain(self.turns.iter().map(|t| t.message.clone())).collect()
+    }
+
+    pub fn history_tokens(&self) -> u32 {
+        context::estimate_messages("", &self.messages())
+    }
+
+    /// Ask the model a question with the full history as context and record the answer
+    pub async fn send(&mut self, router: &LlmRouter, prompts: &PromptLibrary, task_type: TaskType, content: impl Into<String>) -> Result<LlmResponse> {
+        self.summarize_if_needed(router, prompts).await?;
+
+        let mut messages = self.messages();
+        let content = content.into();
+        messages.push(Message::user(content.clone()));
+
+        let response = router.generate(LlmRequest::new(task_type.clone(), messages)).await?;
+        self.record(task_type, content, response.content.clone());
+        Ok(response)
+    }
+
+    /// Fold all but the most recent turns into the summary once the history is too long
+    pub async fn summarize_if_needed(&mut self, router: &LlmRouter, prompts: &PromptLibrary) -> Result<bool> {
+        if self.history_tokens() <= self.max_history_tokens || self.turns.len() <= self.keep_recent {
+            return Ok(false);
+        }
+
+        let fold = self.turns.len() - self.keep_recent;
+        let transcript = self.turns[..fold].iter()
+            .map(|t| format!("[{:?}] {:?}: {}", t.task_type, t.message.role, t.message.content))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let prompt = prompts.render("conversation_summary", &[
+            ("previous_summary", self.summary.clone().unwrap_or_else(|| "(none)".to_string())),
+            ("transcript", transcript),
+        ])?;
+
+        let response = router.generate(LlmRequest::from_prompt(TaskType::Followup, prompt).with_temperature(0.0)).await?;
+        self.summary = Some(response.content.trim().to_string());
+        self.turns.drain(..fold);
+        self.updated_at = Utc::now();
+        Ok(true)
+    }
+
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(path.as_ref(), serde_json::to_string_pretty(self)?).await?;
+        Ok(())
+    }
+
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let content = fs::read_to_string(path.as_ref()).await?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Load the conversation stored in a run directory, or start a new one
+    pub async fn load_or_new(run_dir: impl AsRef<Path>) -> Self {
+        let path = run_dir.as_ref().join(CONVERSATION_FILE);
+        match Self::load(&path).await {
+            Ok(conversation) => conversation,
+            Err(e) => {
+                if path.exists() {
+                    log::warn!("Starting a new conversation, failed to load {}: {}", path.display(), e);
+                }
+                Self::new()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{LlmConfig, LlmProvider, Provider, Usage};
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    /// Provider stub that records request histories and answers with a numbered reply
+    struct EchoProvider {
+        requests: Mutex<Vec<LlmRequest>>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for EchoProvider {
+        async fn generate(&self, request: &LlmRequest) -> Result<LlmResponse> {
+            let mut requests = self.requests.lock().unwrap();
+            requests.push(request.clone());
+            Ok(LlmResponse {
+                id: request.id,
+                provider: Provider::Claude,
+                model: "mock".to_string(),
+                content: format!("reply {}", requests.len()),
+                usage: Usage { prompt_tokens: 1, completion_tokens: 1, total_tokens: 2, ..Default::default() },
+                duration_ms: 1,
+                cost_cents: Some(0.0),
+                rate_limit: None,
+                cache_hit: false,
+            })
+        }
+
+        fn provider_name(&self) -> Provider {
+            Provider::Claude
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+    }
+
+    async fn router(log_dir: &Path) -> (LlmRouter, Arc<EchoProvider>) {
+        let mut router = LlmRouter::new(LlmConfig::default(), log_dir.to_str().unwrap()).await.unwrap();
+        let provider = Arc::new(EchoProvider { requests: Mutex::new(Vec::new()) });
+        router.register_provider(provider.clone());
+        (router, provider)
+    }
+
+    #[tokio::test]
+    async fn test_followup_sees_previous_review() {
+        let temp_dir = TempDir::new().unwrap();
+        let (router, provider) = router(temp_dir.path()).await;
+
+        let mut conversation = Conversation::new();
+        conversation.record(TaskType::Review, "Review the changes", "SUMMARY: the parser leaks memory");
+
+        let answer = conversation.send(&router, &PromptLibrary::builtin(), TaskType::Followup, "How do I fix the leak?").await.unwrap();
+        assert_eq!(answer.content, "reply 1");
+
+        let sent = &provider.requests.lock().unwrap()[0];
+        assert_eq!(sent.task_type, TaskType::Followup);
+        assert_eq!(sent.messages.len(), 3);
+        assert!(sent.messages[1].content.contains("parser leaks memory"));
+        assert_eq!(conversation.turns.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_long_history_is_summarized() {
+        let temp_dir = TempDir::new().unwrap();
+        let (router, provider) = router(temp_dir.path()).await;
+
+        let mut conversation = Conversation { max_history_tokens: 100, keep_recent: 2, ..Conversation::new() };
+        for i in 0..4 {
+            conversation.record(TaskType::Plan, format!("question {} {}", i, "x".repeat(200)), format!("answer {}", i));
+        }
+
+        conversation.send(&router, &PromptLibrary::builtin(), TaskType::Followup, "What next?").await.unwrap();
+
+        let requests = provider.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].prompt_template.as_ref().unwrap().id, "conversation_summary");
+        assert!(requests[0].messages[0].content.contains("question 0"));
+
+        assert_eq!(conversation.summary.as_deref(), Some("reply 1"));
+        let followup = &requests[1].messages;
+        assert!(followup[0].content.starts_with("Summary of earlier conversation"));
+        assert!(followup[1].content.starts_with("question 3"));
+        assert_eq!(followup.last().unwrap().content, "What next?");
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load_with_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut conversation = Conversation::new();
+        conversation.record(TaskType::Plan, "Plan it", "Three tasks");
+        conversation.save(temp_dir.path().join(CONVERSATION_FILE)).await.unwrap();
+
+        let loaded = Conversation::load_or_new(temp_dir.path()).await;
+        assert_eq!(loaded.id, conversation.id);
+        assert_eq!(loaded.turns.len(), 2);
+
+        let fresh = Conversation::load_or_new(temp_dir.path().join("other")).await;
+        assert!(fresh.turns.is_empty());
+    }
+}
@@ -0,0 +1,379 @@
+use anyhow::{anyhow, bail, Result};
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+use super::{LlmConfig, Provider, ProviderConfig};
+
+/// Overrides the keystore location
+pub const KEYSTORE_PATH_ENV: &str = "DESKAGENT_KEYSTORE";
+/// Passphrase used to unlock the keystore without prompting
+pub const KEYSTORE_PASSPHRASE_ENV: &str = "DESKAGENT_KEYSTORE_PASSPHRASE";
+
+const KEYSTORE_VERSION: u32 = 1;
+const CREDENTIAL_PROCESS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A credential that never appears in `Debug`, `Display` or serialized output
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The plaintext value, for the one place that has to send it
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// `${VAR}` placeholder naming an environment variable
+    fn env_placeholder(&self) -> Option<&str> {
+        self.0.strip_prefix("${").and_then(|rest| rest.strip_suffix('}'))
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+impl std::fmt::Display for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl Serialize for Secret {
+    /// Placeholders are configuration, not secrets, so they round-trip; literal keys do not
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self.env_placeholder() {
+            Some(_) => serializer.serialize_str(&self.0),
+            None if self.0.is_empty() => serializer.serialize_str(""),
+            None => serializer.serialize_str("***"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+/// Where a provider's API key comes from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialSource {
+    /// Environment variable name
+    Env(String),
+    /// Entry name in the encrypted keystore
+    Keystore(String),
+    /// Shell command whose trimmed stdout is the key
+    CredentialProcess(String),
+}
+
+/// Resolve a provider's key: an explicit `credential` source, then a `${VAR}` placeholder,
+/// then a literal `api_key`, then a keystore entry named after the provider
+pub async fn resolve(provider: &Provider, config: &ProviderConfig) -> Result<Secret> {
+    if let Some(source) = &config.credential {
+        return resolve_source(source).await;
+    }
+    if let Some(var) = config.api_key.env_placeholder() {
+        return Ok(Secret::new(std::env::var(var).unwrap_or_default()));
+    }
+    if !config.api_key.is_empty() {
+        log::warn!("Plaintext api_key configured for {}; prefer a credential source", provider);
+        return Ok(config.api_key.clone());
+    }
+
+    let keystore = Keystore::open(Keystore::default_path()).await?;
+    match (keystore.contains(&provider.to_string()), passphrase_from_env()) {
+        (true, Some(passphrase)) => Ok(keystore.get(&provider.to_string(), &passphrase)?.unwrap_or_default()),
+        _ => Ok(Secret::default()),
+    }
+}
+
+async fn resolve_source(source: &CredentialSource) -> Result<Secret> {
+    match source {
+        CredentialSource::Env(var) => std::env::var(var)
+            .map(Secret::new)
+            .map_err(|_| anyhow!("Environment variable {} is not set", var)),
+        CredentialSource::Keystore(name) => {
+            let passphrase = passphrase_from_env()
+                .ok_or_else(|| anyhow!("Set {} to unlock the keystore", KEYSTORE_PASSPHRASE_ENV))?;
+            Keystore::open(Keystore::default_path()).await?
+                .get(name, &passphrase)?
+                .ok_or_else(|| anyhow!("No keystore entry named '{}'", name))
+        }
+        CredentialSource::CredentialProcess(command) => run_credential_process(command).await,
+    }
+}
+
+async fn run_credential_process(command: &str) -> Result<Secret> {
+    let output = timeout(CREDENTIAL_PROCESS_TIMEOUT, Command::new("sh").arg("-c").arg(command).kill_on_drop(true).output())
+        .await
+        .map_err(|_| anyhow!("credential_process timed out after {:?}", CREDENTIAL_PROCESS_TIMEOUT))??;
+
+    if !output.status.success() {
+        bail!("credential_process exited with {}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let key = String::from_utf8(output.stdout)
+        .map_err(|_| anyhow!("credential_process printed invalid UTF-8"))?;
+    let key = key.trim();
+    if key.is_empty() {
+        bail!("credential_process printed nothing");
+    }
+    Ok(Secret::new(key))
+}
+
+/// Replace every provider's configured key with the resolved one. Providers whose key
+/// cannot be resolved are left without one and report as unavailable.
+pub async fn resolve_all(config: &mut LlmConfig) {
+    for (provider, provider_config) in config.providers.iter_mut() {
+        provider_config.api_key = match resolve(provider, provider_config).await {
+            Ok(key) => key,
+            Err(e) => {
+                log::warn!("No API key for {}: {}", provider, e);
+                Secret::default()
+            }
+        };
+    }
+}
+
+pub fn passphrase_from_env() -> Option<Secret> {
+    std::env::var(KEYSTORE_PASSPHRASE_ENV).ok().filter(|p| !p.is_empty()).map(Secret::new)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KeystoreFile {
+    version: u32,
+    /// Argon2 salt, hex encoded
+    salt: String,
+    entries: BTreeMap<String, KeystoreEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KeystoreEntry {
+    nonce: String,
+    ciphertext: String,
+    created_at: DateTime<Utc>,
+}
+
+/// API keys encrypted with ChaCha20-Poly1305 under a passphrase-derived (Argon2) key
+#[derive(Debug)]
+pub struct Keystore {
+    path: PathBuf,
+    file: KeystoreFile,
+}
+
+impl Keystore {
+    /// `$DESKAGENT_KEYSTORE`, or `~/.deskagent/keystore.json`
+    pub fn default_path() -> PathBuf {
+        if let Ok(path) = std::env::var(KEYSTORE_PATH_ENV) {
+            return PathBuf::from(path);
+        }
+        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        Path::new(&home).join(".deskagent").join("keystore.json")
+    }
+
+    /// Open a keystore file; a missing file is an empty keystore
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let file = match fs::read_to_string(&path).await {
+            Ok(content) => {
+                let file: KeystoreFile = serde_json::from_str(&content)
+                    .map_err(|e| anyhow!("Invalid keystore {}: {}", path.display(), e))?;
+                if file.version != KEYSTORE_VERSION {
+                    bail!("Unsupported keystore version {} in {}", file.version, path.display());
+                }
+                file
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut salt = [0u8; 16];
+                OsRng.fill_bytes(&mut salt);
+                KeystoreFile { version: KEYSTORE_VERSION, salt: hex::encode(salt), entries: BTreeMap::new() }
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self { path, file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.file.entries.contains_key(name)
+    }
+
+    /// Entry names with their creation time; listing needs no passphrase
+    pub fn list(&self) -> Vec<(&str, DateTime<Utc>)> {
+        self.file.entries.iter().map(|(name, entry)| (name.as_str(), entry.created_at)).collect()
+    }
+
+    pub fn get(&self, name: &str, passphrase: &Secret) -> Result<Option<Secret>> {
+        let Some(entry) = self.file.entries.get(name) else { return Ok(None) };
+        let cipher = self.cipher(passphrase)?;
+
+        let nonce = hex::decode(&entry.nonce)?;
+        if nonce.len() != 12 {
+            bail!("Corrupted keystore entry '{}'", name);
+        }
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: &hex::decode(&entry.ciphertext)?, aad: name.as_bytes() })
+            .map_err(|_| anyhow!("Wrong passphrase or corrupted keystore entry '{}'", name))?;
+
+        let value = String::from_utf8(plaintext).map_err(|_| anyhow!("Corrupted keystore entry '{}'", name))?;
+        Ok(Some(Secret::new(value)))
+    }
+
+    /// Add or replace an entry. The passphrase must unlock the existing entries.
+    pub fn insert(&mut self, name: &str, value: &Secret, passphrase: &Secret) -> Result<()> {
+        if let Some(existing) = self.file.entries.keys().next().cloned() {
+            self.get(&existing, passphrase)?;
+        }
+
+        let cipher = self.cipher(passphrase)?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: value.expose().as_bytes(), aad: name.as_bytes() })
+            .map_err(|_| anyhow!("Failed to encrypt keystore entry '{}'", name))?;
+
+        self.file.entries.insert(name.to_string(), KeystoreEntry {
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+            created_at: Utc::now(),
+        });
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.file.entries.remove(name).is_some()
+    }
+
+    /// Write the keystore, readable only by the current user
+    pub async fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.file)?).await?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&self.path, std::fs::Permissions::from_mode(0o600)).await?;
+        }
+        Ok(())
+    }
+
+    fn cipher(&self, passphrase: &Secret) -> Result<ChaCha20Poly1305> {
+        let salt = hex::decode(&self.file.salt)?;
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.expose().as_bytes(), &salt, &mut key)
+            .map_err(|e| anyhow!("Failed to derive keystore key: {}", e))?;
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+        key.fill(0);
+        Ok(cipher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn provider_config(api_key: &str, credential: Option<CredentialSource>) -> ProviderConfig {
+        ProviderConfig {
+            api_key: Secret::new(api_key),
+            credential,
+            ..LlmConfig::default().providers[&Provider::Claude].clone()
+        }
+    }
+
+    #[test]
+    fn test_secret_never_prints() {
+        let config = provider_config("sk-ant-literal-key", None);
+
+        assert!(!format!("{:?}", config).contains("sk-ant"));
+        assert_eq!(Secret::new("sk-ant-literal-key").to_string(), "***");
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["api_key"], "***");
+
+        // Environment placeholders are not secret and survive a round trip
+        let placeholder = serde_json::to_string(&Secret::new("${ANTHROPIC_API_KEY}")).unwrap();
+        assert_eq!(serde_json::from_str::<Secret>(&placeholder).unwrap().expose(), "${ANTHROPIC_API_KEY}");
+    }
+
+    #[tokio::test]
+    async fn test_keystore_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("keystore.json");
+        let passphrase = Secret::new("correct horse");
+
+        let mut keystore = Keystore::open(&path).await.unwrap();
+        keystore.insert("claude", &Secret::new("sk-ant-stored"), &passphrase).unwrap();
+        keystore.save().await.unwrap();
+
+        let content = fs::read_to_string(&path).await.unwrap();
+        assert!(!content.contains("sk-ant-stored"));
+
+        let mut reopened = Keystore::open(&path).await.unwrap();
+        assert_eq!(reopened.get("claude", &passphrase).unwrap().unwrap().expose(), "sk-ant-stored");
+        assert!(reopened.get("claude", &Secret::new("wrong")).is_err());
+        assert!(reopened.get("missing", &passphrase).unwrap().is_none());
+
+        // A different passphrase cannot add entries to an existing keystore
+        assert!(reopened.insert("openrouter", &Secret::new("sk-or"), &Secret::new("wrong")).is_err());
+        assert_eq!(reopened.list().len(), 1);
+        assert!(reopened.remove("claude"));
+        assert!(reopened.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_sources() {
+        let config = provider_config("", Some(CredentialSource::CredentialProcess("printf ' sk-from-process\\n'".to_string())));
+        assert_eq!(resolve(&Provider::Claude, &config).await.unwrap().expose(), "sk-from-process");
+
+        let failing = provider_config("", Some(CredentialSource::CredentialProcess("echo nope >&2; exit 3".to_string())));
+        let error = resolve(&Provider::Claude, &failing).await.unwrap_err().to_string();
+        assert!(error.contains("nope"));
+
+        let missing_env = provider_config("", Some(CredentialSource::Env("DESKAGENT_TEST_UNSET_KEY".to_string())));
+        assert!(resolve(&Provider::Claude, &missing_env).await.is_err());
+
+        let literal = provider_config("sk-literal", None);
+        assert_eq!(resolve(&Provider::Claude, &literal).await.unwrap().expose(), "sk-literal");
+
+        let placeholder = provider_config("${DESKAGENT_TEST_UNSET_KEY}", None);
+        assert!(resolve(&Provider::Claude, &placeholder).await.unwrap().is_empty());
+    }
+}
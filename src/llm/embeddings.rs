@@ -0,0 +1,134 @@
+use anyhow::Result;
+
+use super::{LlmError, LlmProvider, LlmRequest, LlmResponse, Provider};
+
+/// Vector size of the default `HashingEmbedder`
+pub const HASHING_DIMENSIONS: usize = 256;
+
+/// Offline embedder that hashes words and identifier parts into a fixed-size vector.
+/// It needs no network and is stable across runs, so stored vectors stay comparable.
+#[derive(Debug, Clone)]
+pub struct HashingEmbedder {
+    dimensions: usize,
+}
+
+impl HashingEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self { dimensions: dimensions.max(1) }
+    }
+
+    /// Identifies the vector space; indexes built with another model must be rebuilt
+    pub fn model(&self) -> String {
+        format!("hashing-v1-{}", self.dimensions)
+    }
+
+    pub fn embed_text(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0f32; self.dimensions];
+        for token in tokens(text) {
+            let hash = fnv1a(token.as_bytes());
+            let bucket = (hash % self.dimensions as u64) as usize;
+            // The sign bit spreads collisions out instead of letting them pile up
+            vector[bucket] += if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self::new(HASHING_DIMENSIONS)
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmProvider for HashingEmbedder {
+    async fn generate(&self, _request: &LlmRequest) -> Result<LlmResponse> {
+        Err(LlmError::RequestFailed { message: "The hashing embedder cannot generate text".to_string() }.into())
+    }
+
+    fn provider_name(&self) -> Provider {
+        Provider::Offline
+    }
+
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| self.embed_text(text)).collect())
+    }
+}
+
+/// Cosine similarity; 0 when either vector is all zeros or the lengths differ
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|y| y * y).sum::<f32>().sqrt();
+    if norm == 0.0 { 0.0 } else { dot / norm }
+}
+
+/// Lowercased words plus the parts of camelCase and snake_case identifiers
+fn tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric() && c != '_').filter(|w| w.len() > 1) {
+        tokens.push(word.to_lowercase());
+
+        let mut part = String::new();
+        let mut parts = Vec::new();
+        let mut previous_lower = false;
+        for c in word.chars() {
+            if c == '_' || (c.is_uppercase() && previous_lower) {
+                parts.push(std::mem::take(&mut part));
+            }
+            if c != '_' {
+                part.extend(c.to_lowercase());
+            }
+            previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        }
+        parts.push(part);
+        if parts.len() > 1 {
+            tokens.extend(parts.into_iter().filter(|p| p.len() > 1));
+        }
+    }
+    tokens
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier_tokens() {
+        assert_eq!(tokens("fn parseDiffStats(x)"), vec!["fn", "parsediffstats", "parse", "diff", "stats"]);
+        assert_eq!(tokens("retry_count"), vec!["retry_count", "retry", "count"]);
+    }
+
+    #[tokio::test]
+    async fn test_related_text_scores_higher() {
+        let embedder = HashingEmbedder::default();
+        let vectors = embedder.embed(&[
+            "Add a circuit breaker to the router health tracker".to_string(),
+            "struct HealthTracker { circuit: CircuitState }".to_string(),
+            "Render the TUI keybinding help screen".to_string(),
+        ]).await.unwrap();
+
+        assert_eq!(vectors[0].len(), HASHING_DIMENSIONS);
+        assert_eq!(vectors[0], embedder.embed_text("Add a circuit breaker to the router health tracker"));
+        assert!(cosine_similarity(&vectors[0], &vectors[1]) > cosine_similarity(&vectors[0], &vectors[2]));
+        assert_eq!(cosine_similarity(&vectors[0], &[0.0; 3]), 0.0);
+    }
+}
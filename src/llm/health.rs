@@ -0,0 +1,333 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use tokio::fs;
+
+use super::Provider;
+
+/// Thresholds for opening and closing a provider's circuit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Number of most recent outcomes the error rate is computed over
+    pub window_size: usize,
+    /// Minimum outcomes in the window before the error rate can open the circuit
+    pub min_requests: usize,
+    /// Error rate (0.0-1.0) at which the circuit opens
+    pub error_rate_threshold: f32,
+    /// How long an open circuit skips the provider before allowing a probe
+    pub open_duration_ms: u64,
+    /// Cool-down applied when a provider reports rate limiting
+    pub rate_limit_cooldown_ms: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            window_size: 20,
+            min_requests: 5,
+            error_rate_threshold: 0.5,
+            open_duration_ms: 60_000,
+            rate_limit_cooldown_ms: 30_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// Provider is skipped until the cool-down expires
+    Open,
+    /// A single probe request is allowed to test recovery
+    HalfOpen,
+}
+
+impl std::fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CircuitState::Closed => write!(f, "closed"),
+            CircuitState::Open => write!(f, "open"),
+            CircuitState::HalfOpen => write!(f, "half-open"),
+        }
+    }
+}
+
+/// Point-in-time view of a provider's health, persisted for the TUI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderHealthSnapshot {
+    pub provider: Provider,
+    pub state: CircuitState,
+    pub error_rate: f32,
+    pub recent_requests: usize,
+    pub open_until: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_success_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+struct ProviderHealth {
+    state: CircuitState,
+    recent: VecDeque<bool>,
+    open_until: Option<DateTime<Utc>>,
+    probe_in_flight: bool,
+    last_error: Option<String>,
+    last_success_at: Option<DateTime<Utc>>,
+}
+
+impl ProviderHealth {
+    fn new() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            recent: VecDeque::new(),
+            open_until: None,
+            probe_in_flight: false,
+            last_error: None,
+            last_success_at: None,
+        }
+    }
+
+    fn error_rate(&self) -> f32 {
+        if self.recent.is_empty() {
+            return 0.0;
+        }
+        let failures = self.recent.iter().filter(|ok| !**ok).count();
+        failures as f32 / self.recent.len() as f32
+    }
+
+    fn push_outcome(&mut self, success: bool, window_size: usize) {
+        self.recent.push_back(success);
+        while self.recent.len() > window_size {
+            self.recent.pop_front();
+        }
+    }
+
+    fn open(&mut self, until: DateTime<Utc>) {
+        self.state = CircuitState::Open;
+        self.open_until = Some(until);
+        self.probe_in_flight = false;
+    }
+}
+
+/// Tracks per-provider outcomes and decides whether a provider may be tried
+#[derive(Debug)]
+pub struct HealthTracker {
+    config: CircuitBreakerConfig,
+    providers: HashMap<Provider, ProviderHealth>,
+}
+
+impl HealthTracker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            providers: HashMap::new(),
+        }
+    }
+
+    /// Whether a request may be sent now. Moves expired open circuits to half-open
+    /// and hands out the single probe slot.
+    pub fn allow_request(&mut self, provider: &Provider, now: DateTime<Utc>) -> bool {
+        let health = self.providers.entry(provider.clone()).or_insert_with(ProviderHealth::new);
+
+        match health.state {
+            CircuitState::Closed => true,
+            CircuitState::Open => {
+                if !matches!(health.open_until, Some(until) if now < until) {
+                    log::info!("Circuit for {} half-open, sending probe", provider);
+                    health.state = CircuitState::HalfOpen;
+                    health.probe_in_flight = true;
+                    true
+                } else {
+                    false
+                }
+            }
+            CircuitState::HalfOpen => {
+                if health.probe_in_flight {
+                    false
+                } else {
+                    health.probe_in_flight = true;
+                    true
+                }
+            }
+        }
+    }
+
+    pub fn record_success(&mut self, provider: &Provider, now: DateTime<Utc>) {
+        let window_size = self.config.window_size;
+        let health = self.providers.entry(provider.clone()).or_insert_with(ProviderHealth::new);
+
+        health.push_outcome(true, window_size);
+        health.last_success_at = Some(now);
+
+        if health.state != CircuitState::Closed {
+            log::info!("Circuit for {} closed after successful probe", provider);
+            health.state = CircuitState::Closed;
+            health.open_until = None;
+            health.probe_in_flight = false;
+            health.recent.clear();
+            health.recent.push_back(true);
+        }
+    }
+
+    pub fn record_failure(&mut self, provider: &Provider, error: &str, now: DateTime<Utc>) {
+        let config = self.config.clone();
+        let health = self.providers.entry(provider.clone()).or_insert_with(ProviderHealth::new);
+
+        health.push_outcome(false, config.window_size);
+        health.last_error = Some(error.to_string());
+
+        let should_open = match health.state {
+            CircuitState::HalfOpen => true,
+            CircuitState::Open => false,
+            CircuitState::Closed => {
+                health.recent.len() >= config.min_requests
+                    && health.error_rate() >= config.error_rate_threshold
+            }
+        };
+
+        if should_open {
+            log::warn!("Opening circuit for {} (error rate {:.0}%)", provider, health.error_rate() * 100.0);
+            health.open(now + Duration::milliseconds(config.open_duration_ms as i64));
+        }
+    }
+
+    /// Rate limiting opens the circuit immediately for the given cool-down, or the configured default
+    pub fn record_rate_limited(&mut self, provider: &Provider, cooldown_ms: Option<u64>, now: DateTime<Utc>) {
+        let config = self.config.clone();
+        let health = self.providers.entry(provider.clone()).or_insert_with(ProviderHealth::new);
+
+        health.push_outcome(false, config.window_size);
+        health.last_error = Some("rate limited".to_string());
+
+        let cooldown_ms = cooldown_ms.unwrap_or(config.rate_limit_cooldown_ms);
+        log::warn!("Provider {} rate limited, skipping for {}ms", provider, cooldown_ms);
+        health.open(now + Duration::milliseconds(cooldown_ms as i64));
+    }
+
+    pub fn state(&self, provider: &Provider) -> CircuitState {
+        self.providers.get(provider).map_or(CircuitState::Closed, |h| h.state)
+    }
+
+    pub fn snapshot(&self, provider: &Provider) -> ProviderHealthSnapshot {
+        let health = self.providers.get(provider).cloned().unwrap_or_else(ProviderHealth::new);
+
+        ProviderHealthSnapshot {
+            provider: provider.clone(),
+            state: health.state,
+            error_rate: health.error_rate(),
+            recent_requests: health.recent.len(),
+            open_until: health.open_until,
+            last_error: health.last_error,
+            last_success_at: health.last_success_at,
+        }
+    }
+}
+
+/// Write provider health snapshots so other processes (the TUI) can display them
+pub async fn write_snapshots(path: impl AsRef<Path>, snapshots: &[ProviderHealthSnapshot]) -> Result<()> {
+    let content = serde_json::to_string_pretty(snapshots)?;
+    fs::write(path.as_ref(), content).await?;
+    Ok(())
+}
+
+/// Read snapshots written by `write_snapshots`. Missing files yield an empty list.
+pub async fn read_snapshots(path: impl AsRef<Path>) -> Result<Vec<ProviderHealthSnapshot>> {
+    match fs::read_to_string(path.as_ref()).await {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn tracker() -> HealthTracker {
+        HealthTracker::new(CircuitBreakerConfig {
+            window_size: 4,
+            min_requests: 2,
+            error_rate_threshold: 0.5,
+            open_duration_ms: 1_000,
+            rate_limit_cooldown_ms: 5_000,
+        })
+    }
+
+    #[test]
+    fn test_error_rate_opens_circuit() {
+        let mut tracker = tracker();
+        let now = Utc::now();
+
+        tracker.record_success(&Provider::Claude, now);
+        tracker.record_failure(&Provider::Claude, "boom", now);
+        assert_eq!(tracker.state(&Provider::Claude), CircuitState::Open);
+        assert!(!tracker.allow_request(&Provider::Claude, now));
+        assert!(tracker.allow_request(&Provider::OpenRouter, now));
+    }
+
+    #[test]
+    fn test_half_open_probe_closes_on_success() {
+        let mut tracker = tracker();
+        let now = Utc::now();
+
+        tracker.record_failure(&Provider::Claude, "boom", now);
+        tracker.record_failure(&Provider::Claude, "boom", now);
+        assert_eq!(tracker.state(&Provider::Claude), CircuitState::Open);
+
+        let later = now + Duration::milliseconds(1_500);
+        assert!(tracker.allow_request(&Provider::Claude, later));
+        assert_eq!(tracker.state(&Provider::Claude), CircuitState::HalfOpen);
+
+        // Only one probe at a time
+        assert!(!tracker.allow_request(&Provider::Claude, later));
+
+        tracker.record_success(&Provider::Claude, later);
+        assert_eq!(tracker.state(&Provider::Claude), CircuitState::Closed);
+        assert_eq!(tracker.snapshot(&Provider::Claude).error_rate, 0.0);
+    }
+
+    #[test]
+    fn test_failed_probe_reopens() {
+        let mut tracker = tracker();
+        let now = Utc::now();
+
+        tracker.record_failure(&Provider::Claude, "boom", now);
+        tracker.record_failure(&Provider::Claude, "boom", now);
+
+        let later = now + Duration::milliseconds(1_500);
+        assert!(tracker.allow_request(&Provider::Claude, later));
+        tracker.record_failure(&Provider::Claude, "still down", later);
+
+        assert_eq!(tracker.state(&Provider::Claude), CircuitState::Open);
+        assert!(!tracker.allow_request(&Provider::Claude, later));
+    }
+
+    #[test]
+    fn test_rate_limit_opens_immediately() {
+        let mut tracker = tracker();
+        let now = Utc::now();
+
+        tracker.record_rate_limited(&Provider::OpenRouter, None, now);
+        assert_eq!(tracker.state(&Provider::OpenRouter), CircuitState::Open);
+        assert!(!tracker.allow_request(&Provider::OpenRouter, now + Duration::milliseconds(4_000)));
+        assert!(tracker.allow_request(&Provider::OpenRouter, now + Duration::milliseconds(5_000)));
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("health.json");
+
+        let mut tracker = tracker();
+        tracker.record_failure(&Provider::Claude, "boom", Utc::now());
+        write_snapshots(&path, &[tracker.snapshot(&Provider::Claude)]).await.unwrap();
+
+        let snapshots = read_snapshots(&path).await.unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].last_error.as_deref(), Some("boom"));
+
+        assert!(read_snapshots(temp_dir.path().join("missing.json")).await.unwrap().is_empty());
+    }
+}
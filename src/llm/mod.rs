@@ -1,6 +1,19 @@
 pub mod router;
 pub mod claude;
 pub mod openrouter;
+pub mod budget;
+pub mod stats;
+pub mod health;
+pub mod ratelimit;
+pub mod cache;
+pub mod prompts;
+pub mod context;
+pub mod conversation;
+pub mod attachment;
+pub mod redact;
+pub mod credentials;
+pub mod race;
+pub mod embeddings;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -10,6 +23,19 @@ use uuid::Uuid;
 pub use router::LlmRouter;
 pub use claude::ClaudeClient;
 pub use openrouter::OpenRouterClient;
+pub use budget::{BudgetConfig, BudgetLimit, BudgetScope, BudgetUnit};
+pub use stats::{RoutingStats, BreakdownStats};
+pub use health::{CircuitBreakerConfig, CircuitState, ProviderHealthSnapshot};
+pub use ratelimit::RateLimitInfo;
+pub use cache::CacheConfig;
+pub use prompts::{PromptLibrary, PromptRef, RenderedPrompt};
+pub use context::{ContextConfig, ContextStrategy};
+pub use conversation::Conversation;
+pub use attachment::Attachment;
+pub use redact::{RedactionConfig, RedactionCounts, RedactionPattern};
+pub use credentials::{CredentialSource, Secret};
+pub use race::{RaceSelection, ResponseScorer, RouteStrategy};
+pub use embeddings::HashingEmbedder;
 
 /// Represents different LLM providers
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -29,24 +55,49 @@ impl std::fmt::Display for Provider {
     }
 }
 
-/// Task types that determine routing strategy
+/// Task types that determine routing strategy. config.yaml spells them in capitals.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum TaskType {
+    #[serde(alias = "PLAN")]
     Plan,
+    #[serde(alias = "REVIEW")]
     Review,
+    #[serde(alias = "STATUS")]
     Status,
+    #[serde(alias = "FOLLOWUP")]
     Followup,
+    #[serde(alias = "APPLY")]
     Apply,
 }
 
 /// Configuration for a specific provider
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderConfig {
-    pub api_key: String,
+    /// Literal key or `${ENV_VAR}` placeholder; replaced by the resolved key when the router starts
+    #[serde(default)]
+    pub api_key: Secret,
+    /// Fetch the key from the environment, the keystore or a `credential_process` instead
+    #[serde(default)]
+    pub credential: Option<CredentialSource>,
     pub base_url: String,
     pub model: String,
     pub max_tokens: u32,
     pub timeout_ms: u64,
+    /// Client-side request cap; `None` leaves pacing to the provider
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    /// Client-side token cap, counted from estimated prompt size plus `max_tokens`
+    #[serde(default)]
+    pub tokens_per_minute: Option<u32>,
+    /// Total prompt + completion tokens the model accepts; `None` uses a per-model default
+    #[serde(default)]
+    pub context_window: Option<u32>,
+    /// Model used for `LlmProvider::embed`; `None` when the provider is not used for embeddings
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+    /// Whether the models accept image attachments; `None` decides by model family
+    #[serde(default)]
+    pub accepts_images: Option<bool>,
 }
 
 /// Routing strategy for task types
@@ -54,6 +105,74 @@ pub struct ProviderConfig {
 pub struct RouteConfig {
     pub provider: Provider,
     pub temperature: f32,
+    /// Cheaper model on the same provider used when a budget is close to its limit
+    #[serde(default)]
+    pub budget_model: Option<String>,
+    /// Ordered provider+model attempts; when empty the chain is derived from `provider`
+    #[serde(default)]
+    pub candidates: Vec<RouteCandidate>,
+    /// Try candidates in turn, or race several of them in parallel
+    #[serde(default)]
+    pub strategy: RouteStrategy,
+}
+
+/// One step of a route's fallback chain
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RouteCandidate {
+    pub provider: Provider,
+    /// Model to request; `None` uses the provider's configured model
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Overrides the route temperature for this candidate
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Additional attempts on this candidate before moving to the next
+    #[serde(default)]
+    pub retries: u32,
+}
+
+impl RouteCandidate {
+    pub fn new(provider: Provider) -> Self {
+        Self {
+            provider,
+            model: None,
+            temperature: None,
+            max_tokens: None,
+            retries: 0,
+        }
+    }
+    
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+    
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+}
+
+impl RouteConfig {
+    /// Ordered candidates to try. Routes without explicit candidates start with `provider`
+    /// and fall back to the other `providers` in name order, at most `max_fallbacks` of them.
+    pub fn fallback_chain(&self, providers: &[Provider], max_fallbacks: usize) -> Vec<RouteCandidate> {
+        if !self.candidates.is_empty() {
+            return self.candidates.clone();
+        }
+        
+        let mut fallbacks: Vec<&Provider> = providers.iter()
+            .filter(|p| **p != self.provider && **p != Provider::Offline)
+            .collect();
+        fallbacks.sort_by_key(|p| p.to_string());
+        fallbacks.dedup();
+        
+        std::iter::once(RouteCandidate::new(self.provider.clone()))
+            .chain(fallbacks.into_iter().take(max_fallbacks).map(|p| RouteCandidate::new(p.clone())))
+            .collect()
+    }
 }
 
 /// Complete LLM configuration
@@ -65,6 +184,26 @@ pub struct LlmConfig {
     pub providers: std::collections::HashMap<Provider, ProviderConfig>,
     pub routing: std::collections::HashMap<TaskType, RouteConfig>,
     pub offline_mode: bool,
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// Longest provider-requested wait the router sleeps through before retrying the same provider
+    #[serde(default = "default_max_rate_limit_wait_ms")]
+    pub max_rate_limit_wait_ms: u64,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub context: ContextConfig,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    /// Provider whose `embedding_model` embeds the repository index; `None` uses the local hashing embedder
+    #[serde(default)]
+    pub embedding_provider: Option<Provider>,
+}
+
+fn default_max_rate_limit_wait_ms() -> u64 {
+    10_000
 }
 
 impl Default for LlmConfig {
@@ -74,45 +213,72 @@ impl Default for LlmConfig {
         
         // Default provider configs (will be overridden by config file)
         providers.insert(Provider::Claude, ProviderConfig {
-            api_key: "${ANTHROPIC_API_KEY}".to_string(),
+            api_key: Secret::new("${ANTHROPIC_API_KEY}"),
+            credential: None,
             base_url: "https://api.anthropic.com/v1".to_string(),
             model: "claude-3-5-sonnet-20241022".to_string(),
             max_tokens: 4096,
             timeout_ms: 30000,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            context_window: None,
+            embedding_model: None,
+            accepts_images: None,
         });
         
         providers.insert(Provider::OpenRouter, ProviderConfig {
-            api_key: "${OPENROUTER_API_KEY}".to_string(),
+            api_key: Secret::new("${OPENROUTER_API_KEY}"),
+            credential: None,
             base_url: "https://openrouter.ai/api/v1".to_string(),
             model: "anthropic/claude-3.5-sonnet".to_string(),
             max_tokens: 4096,
             timeout_ms: 30000,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            context_window: None,
+            embedding_model: None,
+            accepts_images: None,
         });
         
         // Default routing strategies
         routing.insert(TaskType::Plan, RouteConfig {
             provider: Provider::Claude,
             temperature: 0.3,
+            budget_model: Some("claude-3-5-haiku-20241022".to_string()),
+            candidates: Vec::new(),
+            strategy: RouteStrategy::Fallback,
         });
         
         routing.insert(TaskType::Review, RouteConfig {
             provider: Provider::Claude,
             temperature: 0.1,
+            budget_model: Some("claude-3-5-haiku-20241022".to_string()),
+            candidates: Vec::new(),
+            strategy: RouteStrategy::Fallback,
         });
         
         routing.insert(TaskType::Status, RouteConfig {
             provider: Provider::OpenRouter,
             temperature: 0.0,
+            budget_model: Some("anthropic/claude-3.5-haiku".to_string()),
+            candidates: Vec::new(),
+            strategy: RouteStrategy::Fallback,
         });
         
         routing.insert(TaskType::Followup, RouteConfig {
             provider: Provider::Claude,
             temperature: 0.2,
+            budget_model: Some("claude-3-5-haiku-20241022".to_string()),
+            candidates: Vec::new(),
+            strategy: RouteStrategy::Fallback,
         });
         
         routing.insert(TaskType::Apply, RouteConfig {
             provider: Provider::Claude,
             temperature: 0.0,
+            budget_model: Some("claude-3-5-haiku-20241022".to_string()),
+            candidates: Vec::new(),
+            strategy: RouteStrategy::Fallback,
         });
         
         Self {
@@ -122,6 +288,13 @@ impl Default for LlmConfig {
             providers,
             routing,
             offline_mode: false,
+            budget: BudgetConfig::default(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+            max_rate_limit_wait_ms: default_max_rate_limit_wait_ms(),
+            cache: CacheConfig::default(),
+            context: ContextConfig::default(),
+            redaction: RedactionConfig::default(),
+            embedding_provider: None,
         }
     }
 }
@@ -134,6 +307,18 @@ pub struct LlmRequest {
     pub messages: Vec<Message>,
     pub temperature: Option<f32>,
     pub max_tokens: Option<u32>,
+    /// Overrides the provider's configured model for this request
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Skip cached responses; a fresh response still refreshes the cache
+    #[serde(default)]
+    pub bypass_cache: bool,
+    /// Template the prompt was rendered from, recorded in the routing log
+    #[serde(default)]
+    pub prompt_template: Option<PromptRef>,
+    /// Secrets the router replaced before dispatch, recorded in the routing log
+    #[serde(default)]
+    pub redactions: RedactionCounts,
 }
 
 /// Message in conversation
@@ -141,6 +326,12 @@ pub struct LlmRequest {
 pub struct Message {
     pub role: MessageRole,
     pub content: String,
+    /// Ask providers that support prompt caching to cache the prompt up to and including this message
+    #[serde(default)]
+    pub cache_breakpoint: bool,
+    /// Images and file excerpts sent after `content`
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
 }
 
 /// Message roles
@@ -160,15 +351,29 @@ pub struct LlmResponse {
     pub content: String,
     pub usage: Usage,
     pub duration_ms: u64,
-    pub cost_cents: Option<u32>,
+    /// Fractional cents; rounded only for display
+    pub cost_cents: Option<f64>,
+    /// Rate-limit headers returned alongside the response, if any
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitInfo>,
+    /// Served from the response cache; usage and cost are zero
+    #[serde(default)]
+    pub cache_hit: bool,
 }
 
 /// Token usage information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Usage {
+    /// All input tokens, including those written to or read from the prompt cache
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    /// Input tokens written to the provider's prompt cache
+    #[serde(default)]
+    pub cache_creation_input_tokens: u32,
+    /// Input tokens served from the provider's prompt cache
+    #[serde(default)]
+    pub cache_read_input_tokens: u32,
 }
 
 /// Provider-agnostic trait for LLM clients
@@ -179,6 +384,11 @@ pub trait LlmProvider {
     fn provider_name(&self) -> Provider;
     
     fn is_available(&self) -> bool;
+    
+    /// One vector per input text. Providers without an embeddings API return `EmbeddingsNotSupported`.
+    async fn embed(&self, _texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Err(LlmError::EmbeddingsNotSupported { provider: self.provider_name() }.into())
+    }
 }
 
 /// Errors that can occur during LLM operations
@@ -191,7 +401,13 @@ pub enum LlmError {
     RequestFailed { message: String },
     
     #[error("Rate limit exceeded for provider: {provider}")]
-    RateLimited { provider: Provider },
+    RateLimited { provider: Provider, info: RateLimitInfo },
+    
+    #[error("Prompt of ~{estimated} tokens does not fit the {limit}-token input budget of {provider}")]
+    ContextOverflow { provider: Provider, estimated: u32, limit: u32 },
+    
+    #[error("Circuit open for provider: {provider}")]
+    CircuitOpen { provider: Provider },
     
     #[error("Invalid configuration: {message}")]
     InvalidConfig { message: String },
@@ -201,6 +417,15 @@ pub enum LlmError {
     
     #[error("Maximum retries exceeded")]
     MaxRetriesExceeded,
+    
+    #[error("Budget exceeded for {scope}: spent {} of {} {unit}", .unit.format_amount(*.spent), .unit.format_amount(*.limit))]
+    BudgetExceeded { scope: BudgetScope, unit: BudgetUnit, spent: f64, limit: f64 },
+    
+    #[error("Request blocked by strict redaction, prompt contains secrets: {kinds}")]
+    SecretsDetected { kinds: String },
+    
+    #[error("Provider {provider} has no embeddings model configured")]
+    EmbeddingsNotSupported { provider: Provider },
 }
 
 impl LlmRequest {
@@ -211,9 +436,20 @@ impl LlmRequest {
             messages,
             temperature: None,
             max_tokens: None,
+            model: None,
+            bypass_cache: false,
+            prompt_template: None,
+            redactions: RedactionCounts::new(),
         }
     }
     
+    /// Single user-message request built from a rendered template
+    pub fn from_prompt(task_type: TaskType, prompt: RenderedPrompt) -> Self {
+        let mut request = Self::new(task_type, vec![Message::user(prompt.text)]);
+        request.prompt_template = Some(prompt.template);
+        request
+    }
+    
     pub fn with_temperature(mut self, temperature: f32) -> Self {
         self.temperature = Some(temperature);
         self
@@ -223,6 +459,16 @@ impl LlmRequest {
         self.max_tokens = Some(max_tokens);
         self
     }
+    
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+    
+    pub fn bypass_cache(mut self) -> Self {
+        self.bypass_cache = true;
+        self
+    }
 }
 
 impl Message {
@@ -230,6 +476,8 @@ impl Message {
         Self {
             role: MessageRole::User,
             content,
+            cache_breakpoint: false,
+            attachments: Vec::new(),
         }
     }
     
@@ -237,6 +485,8 @@ impl Message {
         Self {
             role: MessageRole::Assistant,
             content,
+            cache_breakpoint: false,
+            attachments: Vec::new(),
         }
     }
     
@@ -244,6 +494,19 @@ impl Message {
         Self {
             role: MessageRole::System,
             content,
+            cache_breakpoint: false,
+            attachments: Vec::new(),
         }
     }
+    
+    /// Mark the end of a stable prompt prefix worth caching
+    pub fn with_cache_breakpoint(mut self) -> Self {
+        self.cache_breakpoint = true;
+        self
+    }
+    
+    pub fn with_attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
 }
\ No newline at end of file
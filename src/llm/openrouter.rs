@@ -6,7 +6,7 @@ use tokio::time::Duration;
 
 use super::{
     LlmProvider, LlmRequest, LlmResponse, Message, MessageRole, Provider, 
-    ProviderConfig, Usage, LlmError
+    ProviderConfig, Usage, LlmError, RateLimitInfo, Attachment
 };
 
 #[derive(Debug)]
@@ -26,7 +26,39 @@ struct OpenRouterApiRequest {
 #[derive(Debug, Serialize)]
 struct OpenRouterMessage {
     role: String,
-    content: String,
+    content: OpenRouterMessageContent,
+}
+
+/// Plain text, or content parts when the message has attachments
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(untagged)]
+enum OpenRouterMessageContent {
+    Text(String),
+    Parts(Vec<OpenRouterContentPart>),
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OpenRouterContentPart {
+    Text { text: String },
+    ImageUrl { image_url: OpenRouterImageUrl },
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct OpenRouterImageUrl {
+    url: String,
+}
+
+impl OpenRouterContentPart {
+    fn from_attachment(attachment: &Attachment) -> Self {
+        match attachment {
+            // Images are inlined as data URLs
+            Attachment::Image { media_type, data } => Self::ImageUrl {
+                image_url: OpenRouterImageUrl { url: format!("data:{};base64,{}", media_type, data) },
+            },
+            Attachment::File { .. } => Self::Text { text: attachment.as_text().unwrap_or_default() },
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -56,6 +88,23 @@ struct OpenRouterResponseMessage {
     content: String,
 }
 
+#[derive(Debug, Serialize)]
+struct OpenRouterEmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenRouterEmbeddingResponse {
+    data: Vec<OpenRouterEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenRouterEmbedding {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
 #[derive(Debug, Deserialize)]
 struct OpenRouterErrorResponse {
     error: OpenRouterError,
@@ -81,14 +130,24 @@ impl OpenRouterClient {
     
     fn build_request(&self, request: &LlmRequest) -> OpenRouterApiRequest {
         let messages = request.messages.iter().map(|msg| {
+            let content = if msg.attachments.is_empty() {
+                OpenRouterMessageContent::Text(msg.content.clone())
+            } else {
+                let text = (!msg.content.is_empty()).then(|| OpenRouterContentPart::Text { text: msg.content.clone() });
+                OpenRouterMessageContent::Parts(
+                    text.into_iter()
+                        .chain(msg.attachments.iter().map(OpenRouterContentPart::from_attachment))
+                        .collect()
+                )
+            };
             OpenRouterMessage {
                 role: self.map_message_role(&msg.role),
-                content: msg.content.clone(),
+                content,
             }
         }).collect();
         
         OpenRouterApiRequest {
-            model: self.config.model.clone(),
+            model: request.model.clone().unwrap_or_else(|| self.config.model.clone()),
             max_tokens: request.max_tokens.unwrap_or(self.config.max_tokens),
             temperature: request.temperature.unwrap_or(0.7),
             messages,
@@ -103,13 +162,13 @@ impl OpenRouterClient {
         }
     }
     
-    async fn make_api_request(&self, openrouter_request: &OpenRouterApiRequest) -> Result<OpenRouterApiResponse> {
+    async fn make_api_request(&self, openrouter_request: &OpenRouterApiRequest) -> Result<(OpenRouterApiResponse, RateLimitInfo)> {
         let url = format!("{}/chat/completions", self.config.base_url);
         
         let response = self.client
             .post(&url)
             .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
+            .header("Authorization", format!("Bearer {}", self.config.api_key.expose()))
             .header("HTTP-Referer", "https://github.com/anthropics/deskagent")
             .header("X-Title", "DeskAgent")
             .json(openrouter_request)
@@ -118,19 +177,21 @@ impl OpenRouterClient {
             .map_err(|e| anyhow!("HTTP request failed: {}", e))?;
         
         let status = response.status();
+        let rate_limit = RateLimitInfo::from_openrouter_headers(response.headers());
         let response_text = response.text().await
             .map_err(|e| anyhow!("Failed to read response: {}", e))?;
         
         if status.is_success() {
             serde_json::from_str::<OpenRouterApiResponse>(&response_text)
+                .map(|parsed| (parsed, rate_limit))
                 .map_err(|e| anyhow!("Failed to parse OpenRouter response: {}", e))
+        } else if status.as_u16() == 429 {
+            Err(LlmError::RateLimited { provider: Provider::OpenRouter, info: rate_limit }.into())
         } else {
             // Try to parse as error response
             if let Ok(error_response) = serde_json::from_str::<OpenRouterErrorResponse>(&response_text) {
-                if let Some(code) = &error_response.error.code {
-                    if code == "rate_limit_exceeded" || status.as_u16() == 429 {
-                        return Err(LlmError::RateLimited { provider: Provider::OpenRouter }.into());
-                    }
+                if error_response.error.code.as_deref() == Some("rate_limit_exceeded") {
+                    return Err(LlmError::RateLimited { provider: Provider::OpenRouter, info: rate_limit }.into());
                 }
                 Err(LlmError::RequestFailed { 
                     message: format!("OpenRouter API error: {}", error_response.error.message) 
@@ -149,13 +210,12 @@ impl OpenRouterClient {
             prompt_tokens: openrouter_response.usage.prompt_tokens,
             completion_tokens: openrouter_response.usage.completion_tokens,
             total_tokens: openrouter_response.usage.total_tokens,
+            ..Default::default()
         };
         
         // Estimate cost based on model - varies by model on OpenRouter
         // Using a conservative estimate of $0.0005/1K tokens for input, $0.002/1K tokens for output
-        let cost_cents = Some(
-            (usage.prompt_tokens as f64 * 0.00005 + usage.completion_tokens as f64 * 0.0002) as u32
-        );
+        let cost_cents = Some(usage.prompt_tokens as f64 * 0.00005 + usage.completion_tokens as f64 * 0.0002);
         
         let response = LlmResponse {
             id: request_id,
@@ -165,6 +225,8 @@ impl OpenRouterClient {
             usage,
             duration_ms,
             cost_cents,
+            rate_limit: None,
+            cache_hit: false,
         };
         
         Ok(response)
@@ -177,10 +239,11 @@ impl LlmProvider for OpenRouterClient {
         let start_time = Instant::now();
         
         let openrouter_request = self.build_request(request);
-        let openrouter_response = self.make_api_request(&openrouter_request).await?;
+        let (openrouter_response, rate_limit) = self.make_api_request(&openrouter_request).await?;
         
         let duration_ms = start_time.elapsed().as_millis() as u64;
-        let response = self.parse_response(request.id, openrouter_response, duration_ms)?;
+        let mut response = self.parse_response(request.id, openrouter_response, duration_ms)?;
+        response.rate_limit = (!rate_limit.is_empty()).then_some(rate_limit);
         
         Ok(response)
     }
@@ -190,7 +253,45 @@ impl LlmProvider for OpenRouterClient {
     }
     
     fn is_available(&self) -> bool {
-        !self.config.api_key.is_empty() && !self.config.api_key.starts_with('$')
+        !self.config.api_key.is_empty() && !self.config.api_key.expose().starts_with('$')
+    }
+    
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let model = self.config.embedding_model.as_deref()
+            .ok_or(LlmError::EmbeddingsNotSupported { provider: Provider::OpenRouter })?;
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let url = format!("{}/embeddings", self.config.base_url);
+        let response = self.client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.config.api_key.expose()))
+            .json(&OpenRouterEmbeddingRequest { model, input: texts })
+            .send()
+            .await
+            .map_err(|e| anyhow!("HTTP request failed: {}", e))?;
+        
+        let status = response.status();
+        let rate_limit = RateLimitInfo::from_openrouter_headers(response.headers());
+        let response_text = response.text().await
+            .map_err(|e| anyhow!("Failed to read response: {}", e))?;
+        
+        if status.as_u16() == 429 {
+            return Err(LlmError::RateLimited { provider: Provider::OpenRouter, info: rate_limit }.into());
+        }
+        if !status.is_success() {
+            return Err(anyhow!("OpenRouter embeddings request failed with status {}: {}", status, response_text));
+        }
+        
+        let mut parsed: OpenRouterEmbeddingResponse = serde_json::from_str(&response_text)
+            .map_err(|e| anyhow!("Failed to parse OpenRouter embeddings: {}", e))?;
+        if parsed.data.len() != texts.len() {
+            return Err(anyhow!("OpenRouter returned {} embeddings for {} inputs", parsed.data.len(), texts.len()));
+        }
+        parsed.data.sort_by_key(|e| e.index);
+        Ok(parsed.data.into_iter().map(|e| e.embedding).collect())
     }
 }
 
@@ -202,11 +303,17 @@ mod tests {
     #[test]
     fn test_openrouter_client_creation() {
         let config = ProviderConfig {
-            api_key: "test-key".to_string(),
+            api_key: "test-key".into(),
+            credential: None,
             base_url: "https://openrouter.ai/api/v1".to_string(),
             model: "anthropic/claude-3.5-sonnet".to_string(),
             max_tokens: 4096,
             timeout_ms: 30000,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            context_window: None,
+            embedding_model: None,
+            accepts_images: None,
         };
         
         let client = OpenRouterClient::new(config);
@@ -217,11 +324,17 @@ mod tests {
     #[test]
     fn test_message_role_mapping() {
         let config = ProviderConfig {
-            api_key: "test-key".to_string(),
+            api_key: "test-key".into(),
+            credential: None,
             base_url: "https://openrouter.ai/api/v1".to_string(),
             model: "anthropic/claude-3.5-sonnet".to_string(),
             max_tokens: 4096,
             timeout_ms: 30000,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            context_window: None,
+            embedding_model: None,
+            accepts_images: None,
         };
         
         let client = OpenRouterClient::new(config);
@@ -234,11 +347,17 @@ mod tests {
     #[test]
     fn test_build_request() {
         let config = ProviderConfig {
-            api_key: "test-key".to_string(),
+            api_key: "test-key".into(),
+            credential: None,
             base_url: "https://openrouter.ai/api/v1".to_string(),
             model: "anthropic/claude-3.5-sonnet".to_string(),
             max_tokens: 4096,
             timeout_ms: 30000,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            context_window: None,
+            embedding_model: None,
+            accepts_images: None,
         };
         
         let client = OpenRouterClient::new(config);
@@ -262,26 +381,66 @@ mod tests {
         assert_eq!(openrouter_request.messages[1].role, "user");
     }
     
+    #[test]
+    fn test_attachments_serialize_as_content_parts() {
+        let client = OpenRouterClient::new(ProviderConfig {
+            api_key: "test-key".into(),
+            credential: None,
+            base_url: "https://openrouter.ai/api/v1".to_string(),
+            model: "anthropic/claude-3.5-sonnet".to_string(),
+            max_tokens: 4096,
+            timeout_ms: 30000,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            context_window: None,
+            embedding_model: None,
+            accepts_images: None,
+        });
+        let message = Message::user("What changed?".to_string())
+            .with_attachment(Attachment::image_base64("image/png", "iVBORw=="))
+            .with_attachment(Attachment::File {
+                path: "src/main.rs".to_string(),
+                start_line: Some(1),
+                end_line: Some(1),
+                content: "fn main() {}".to_string(),
+            });
+        let request = LlmRequest::new(TaskType::Review, vec![message, Message::user("Thanks".to_string())]);
+        
+        let body = serde_json::to_value(client.build_request(&request)).unwrap();
+        assert_eq!(body["messages"][0]["content"], serde_json::json!([
+            {"type": "text", "text": "What changed?"},
+            {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw=="}},
+            {"type": "text", "text": "File: src/main.rs (lines 1-1)\n```\nfn main() {}\n```"},
+        ]));
+        assert_eq!(body["messages"][1]["content"], "Thanks");
+    }
+    
     #[test]
     fn test_availability_check() {
         let mut config = ProviderConfig {
-            api_key: "test-key".to_string(),
+            api_key: "test-key".into(),
+            credential: None,
             base_url: "https://openrouter.ai/api/v1".to_string(),
             model: "anthropic/claude-3.5-sonnet".to_string(),
             max_tokens: 4096,
             timeout_ms: 30000,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            context_window: None,
+            embedding_model: None,
+            accepts_images: None,
         };
         
         let client = OpenRouterClient::new(config.clone());
         assert!(client.is_available());
         
         // Test with empty API key
-        config.api_key = "".to_string();
+        config.api_key = "".into();
         let client = OpenRouterClient::new(config.clone());
         assert!(!client.is_available());
         
         // Test with environment variable placeholder
-        config.api_key = "${OPENROUTER_API_KEY}".to_string();
+        config.api_key = "${OPENROUTER_API_KEY}".into();
         let client = OpenRouterClient::new(config);
         assert!(!client.is_available());
     }
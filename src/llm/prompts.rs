@@ -0,0 +1,275 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use tokio::fs;
+
+/// Templates compiled into the binary, overridden by files in the `prompts/` directory
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("plan", include_str!("../../prompts/plan.md")),
+    ("review", include_str!("../../prompts/review.md")),
+    ("conversation_summary", include_str!("../../prompts/conversation_summary.md")),
+];
+
+const BUILTIN_PARTIALS: &[(&str, &str)] = &[
+    ("rust_guidelines", include_str!("../../prompts/partials/rust_guidelines.md")),
+];
+
+/// Partials may include other partials, up to this depth
+const MAX_PARTIAL_DEPTH: usize = 8;
+
+/// A named prompt with a version bumped whenever its wording changes
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTemplate {
+    pub id: String,
+    pub version: u32,
+    pub description: Option<String>,
+    pub body: String,
+}
+
+/// Identifies which template produced a request, for routing logs and stats
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PromptRef {
+    pub id: String,
+    pub version: u32,
+}
+
+impl std::fmt::Display for PromptRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@v{}", self.id, self.version)
+    }
+}
+
+/// Output of rendering a template
+#[derive(Debug, Clone)]
+pub struct RenderedPrompt {
+    pub template: PromptRef,
+    pub text: String,
+}
+
+impl PromptTemplate {
+    /// Parse a template file. An optional `---` front matter block sets `version` and `description`.
+    pub fn parse(id: &str, source: &str) -> Result<Self> {
+        let mut version = 1;
+        let mut description = None;
+        let mut body = source;
+
+        if let Some(rest) = source.strip_prefix("---\n") {
+            let end = rest.find("\n---\n")
+                .ok_or_else(|| anyhow!("Prompt template '{}' has unterminated front matter", id))?;
+
+            for line in rest[..end].lines().filter(|l| !l.trim().is_empty()) {
+                let (key, value) = line.split_once(':')
+                    .ok_or_else(|| anyhow!("Prompt template '{}' has invalid front matter line: {}", id, line))?;
+                match key.trim() {
+                    "version" => {
+                        version = value.trim().parse()
+                            .map_err(|_| anyhow!("Prompt template '{}' has invalid version: {}", id, value.trim()))?;
+                    }
+                    "description" => description = Some(value.trim().to_string()),
+                    other => log::debug!("Ignoring unknown front matter key '{}' in prompt '{}'", other, id),
+                }
+            }
+
+            body = &rest[end + "\n---\n".len()..];
+        }
+
+        Ok(Self {
+            id: id.to_string(),
+            version,
+            description,
+            body: body.to_string(),
+        })
+    }
+}
+
+/// Named templates and partials available for rendering
+#[derive(Debug, Clone)]
+pub struct PromptLibrary {
+    templates: HashMap<String, PromptTemplate>,
+    partials: HashMap<String, String>,
+}
+
+impl PromptLibrary {
+    /// Library containing only the built-in templates
+    pub fn builtin() -> Self {
+        let templates = BUILTIN_TEMPLATES.iter()
+            .map(|(id, source)| {
+                let template = PromptTemplate::parse(id, source).expect("built-in prompt templates are valid");
+                (id.to_string(), template)
+            })
+            .collect();
+        let partials = BUILTIN_PARTIALS.iter()
+            .map(|(name, source)| (name.to_string(), source.trim_end().to_string()))
+            .collect();
+
+        Self { templates, partials }
+    }
+
+    /// Built-in templates overlaid with `<dir>/*.md` and `<dir>/partials/*.md`. A missing directory is not an error.
+    pub async fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let mut library = Self::builtin();
+        let dir = dir.as_ref();
+
+        for (id, source) in read_markdown_files(dir).await? {
+            let template = PromptTemplate::parse(&id, &source)?;
+            library.templates.insert(id, template);
+        }
+        for (name, source) in read_markdown_files(&dir.join("partials")).await? {
+            library.partials.insert(name, source.trim_end().to_string());
+        }
+
+        Ok(library)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&PromptTemplate> {
+        self.templates.get(id)
+    }
+
+    /// Render a template, expanding `{{> partial}}` includes and `{{variable}}` placeholders.
+    /// Unknown variables or partials are errors so typos never reach the model.
+    pub fn render(&self, id: &str, vars: &[(&str, String)]) -> Result<RenderedPrompt> {
+        let template = self.get(id).ok_or_else(|| anyhow!("Unknown prompt template: {}", id))?;
+        let vars: HashMap<&str, &str> = vars.iter().map(|(k, v)| (*k, v.as_str())).collect();
+
+        let expanded = self.expand_partials(&template.body, 0)?;
+        let text = substitute(&expanded, |name| {
+            vars.get(name).copied()
+                .ok_or_else(|| anyhow!("Prompt template '{}' references unset variable '{}'", id, name))
+        })?;
+
+        Ok(RenderedPrompt {
+            template: PromptRef { id: template.id.clone(), version: template.version },
+            text: text.trim_end().to_string(),
+        })
+    }
+
+    fn expand_partials(&self, body: &str, depth: usize) -> Result<String> {
+        if depth > MAX_PARTIAL_DEPTH {
+            bail!("Prompt partials nested deeper than {} levels", MAX_PARTIAL_DEPTH);
+        }
+
+        let mut output = String::with_capacity(body.len());
+        let mut rest = body;
+        while let Some(start) = rest.find("{{>") {
+            let end = rest[start..].find("}}")
+                .ok_or_else(|| anyhow!("Unterminated partial include in prompt template"))? + start;
+            let name = rest[start + 3..end].trim();
+            let partial = self.partials.get(name)
+                .ok_or_else(|| anyhow!("Unknown prompt partial: {}", name))?;
+
+            output.push_str(&rest[..start]);
+            output.push_str(&self.expand_partials(partial, depth + 1)?);
+            rest = &rest[end + 2..];
+        }
+        output.push_str(rest);
+
+        Ok(output)
+    }
+}
+
+/// Replace every `{{name}}` with the value returned by `lookup`
+fn substitute<'a>(body: &str, lookup: impl Fn(&str) -> Result<&'a str>) -> Result<String> {
+    let mut output = String::with_capacity(body.len());
+    let mut rest = body;
+
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..].find("}}")
+            .ok_or_else(|| anyhow!("Unterminated variable in prompt template"))? + start;
+
+        output.push_str(&rest[..start]);
+        output.push_str(lookup(rest[start + 2..end].trim())?);
+        rest = &rest[end + 2..];
+    }
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+async fn read_markdown_files(dir: &Path) -> Result<Vec<(String, String)>> {
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut files = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("md") {
+            continue;
+        }
+        if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+            files.push((stem.to_string(), fs::read_to_string(&path).await?));
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_front_matter() {
+        let template = PromptTemplate::parse("greet", "---\nversion: 3\ndescription: Say hi\n---\nHello {{name}}\n").unwrap();
+        assert_eq!(template.version, 3);
+        assert_eq!(template.description.as_deref(), Some("Say hi"));
+        assert_eq!(template.body, "Hello {{name}}\n");
+
+        let bare = PromptTemplate::parse("bare", "No front matter").unwrap();
+        assert_eq!(bare.version, 1);
+        assert!(PromptTemplate::parse("bad", "---\nversion: x\n---\nbody").is_err());
+    }
+
+    #[test]
+    fn test_builtin_plan_renders() {
+        let library = PromptLibrary::builtin();
+        let rendered = library.render("plan", &[("sprint_content", "Ship the cache".to_string())]).unwrap();
+
+        assert_eq!(rendered.template, PromptRef { id: "plan".to_string(), version: 1 });
+        assert!(rendered.text.contains("Ship the cache"));
+        assert!(rendered.text.contains("Rust best practices"));
+        assert!(!rendered.text.contains("{{"));
+    }
+
+    #[test]
+    fn test_missing_variable_and_partial_are_errors() {
+        let library = PromptLibrary::builtin();
+        assert!(library.render("plan", &[]).is_err());
+        assert!(library.render("nope", &[]).is_err());
+
+        let mut library = PromptLibrary::builtin();
+        library.templates.insert("broken".to_string(), PromptTemplate::parse("broken", "{{> missing}}").unwrap());
+        assert!(library.render("broken", &[]).is_err());
+    }
+
+    #[test]
+    fn test_recursive_partials_are_bounded() {
+        let mut library = PromptLibrary::builtin();
+        library.partials.insert("loop".to_string(), "again {{> loop}}".to_string());
+        library.templates.insert("looping".to_string(), PromptTemplate::parse("looping", "{{> loop}}").unwrap());
+        assert!(library.render("looping", &[]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_directory_overrides_builtin() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("partials")).await.unwrap();
+        fs::write(temp_dir.path().join("plan.md"), "---\nversion: 2\n---\n{{> house_style}}\nPlan: {{sprint_content}}").await.unwrap();
+        fs::write(temp_dir.path().join("partials/house_style.md"), "Be brief.\n").await.unwrap();
+
+        let library = PromptLibrary::load(temp_dir.path()).await.unwrap();
+        let rendered = library.render("plan", &[("sprint_content", "x".to_string())]).unwrap();
+        assert_eq!(rendered.template.to_string(), "plan@v2");
+        assert_eq!(rendered.text, "Be brief.\nPlan: x");
+
+        // Templates not overridden keep their built-in versions
+        assert_eq!(library.get("review").unwrap().version, 1);
+
+        let missing = PromptLibrary::load(temp_dir.path().join("missing")).await.unwrap();
+        assert!(missing.get("plan").is_some());
+    }
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::{LlmRequest, LlmResponse, Message, RouteCandidate};
+
+/// Scores a response for a race; `None` marks it invalid
+pub type ResponseScorer = Arc<dyn Fn(&LlmResponse) -> Option<f64> + Send + Sync>;
+
+/// How a route dispatches to its candidates
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteStrategy {
+    /// Try candidates one after another until one succeeds
+    #[default]
+    Fallback,
+    /// Send the request to the first `contestants` candidates in parallel and keep one answer.
+    /// When every contestant fails, the remaining candidates are tried in order.
+    Race {
+        #[serde(default = "default_contestants")]
+        contestants: usize,
+        #[serde(default)]
+        select: RaceSelection,
+    },
+}
+
+/// Which contestant's answer a race keeps
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RaceSelection {
+    /// The earliest response the scorer accepts
+    #[default]
+    FirstValid,
+    /// The accepted response with the highest score; ties go to the earlier one
+    BestScore,
+    /// Another model picks among the accepted responses
+    Judge(RouteCandidate),
+}
+
+fn default_contestants() -> usize {
+    2
+}
+
+/// Default scorer: a response is valid when it contains a JSON object or array
+pub fn structured_score(response: &LlmResponse) -> Option<f64> {
+    let content = response.content.trim();
+    let start = content.find(['{', '['])?;
+    let end = content.rfind(['}', ']'])?;
+    if end < start {
+        return None;
+    }
+    serde_json::from_str::<serde_json::Value>(&content[start..=end]).ok().map(|_| 1.0)
+}
+
+/// Prompt asking a judge model to pick the best of `answers` for `request`
+pub fn judge_messages(request: &LlmRequest, answers: &[&LlmResponse]) -> Vec<Message> {
+    let transcript = request.messages.iter()
+        .map(|m| format!("{:?}: {}", m.role, m.content))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let candidates = answers.iter().enumerate()
+        .map(|(i, answer)| format!("Answer {}:\n{}", i + 1, answer.content))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    vec![Message::user(format!(
+        "Several assistants answered the same request. Pick the answer that is most correct, complete and \
+         follows the requested format. Reply with the answer number only.\n\nRequest:\n{}\n\n{}",
+        transcript, candidates
+    ))]
+}
+
+/// Zero-based index of the answer a judge chose, if it named one of `count` answers
+pub fn parse_verdict(verdict: &str, count: usize) -> Option<usize> {
+    let digits: String = verdict.chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    let choice: usize = digits.parse().ok()?;
+    (1..=count).contains(&choice).then(|| choice - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{Provider, TaskType};
+
+    fn response(content: &str) -> LlmResponse {
+        LlmResponse {
+            id: uuid::Uuid::new_v4(),
+            provider: Provider::Claude,
+            model: "model".to_string(),
+            content: content.to_string(),
+            usage: Default::default(),
+            duration_ms: 0,
+            cost_cents: None,
+            rate_limit: None,
+            cache_hit: false,
+        }
+    }
+
+    #[test]
+    fn test_structured_score() {
+        assert_eq!(structured_score(&response("Here you go:\n```json\n{\"tasks\": []}\n```")), Some(1.0));
+        assert_eq!(structured_score(&response("[1, 2]")), Some(1.0));
+        assert_eq!(structured_score(&response("I could not produce a plan.")), None);
+        assert_eq!(structured_score(&response("{\"tasks\": [")), None);
+    }
+
+    #[test]
+    fn test_judge_prompt_and_verdict() {
+        let request = LlmRequest::new(TaskType::Plan, vec![Message::user("Plan the sprint".to_string())]);
+        let (first, second) = (response("plan A"), response("plan B"));
+        let prompt = &judge_messages(&request, &[&first, &second])[0].content;
+        assert!(prompt.contains("Plan the sprint"));
+        assert!(prompt.contains("Answer 2:\nplan B"));
+
+        assert_eq!(parse_verdict("2", 2), Some(1));
+        assert_eq!(parse_verdict("Answer 1 is best.", 2), Some(0));
+        assert_eq!(parse_verdict("3", 2), None);
+        assert_eq!(parse_verdict("neither", 2), None);
+    }
+
+    #[test]
+    fn test_strategy_config_format() {
+        let race: RouteStrategy = serde_json::from_value(serde_json::json!({
+            "race": { "select": { "judge": { "provider": "Claude", "model": "claude-3-5-haiku-20241022" } } }
+        })).unwrap();
+        match race {
+            RouteStrategy::Race { contestants, select: RaceSelection::Judge(judge) } => {
+                assert_eq!(contestants, 2);
+                assert_eq!(judge.model.as_deref(), Some("claude-3-5-haiku-20241022"));
+            }
+            other => panic!("unexpected strategy {:?}", other),
+        }
+        assert_eq!(serde_json::from_str::<RouteStrategy>("\"fallback\"").unwrap(), RouteStrategy::Fallback);
+    }
+}
@@ -0,0 +1,303 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Rate-limit metadata reported by a provider in its response headers
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitInfo {
+    /// How long the provider asked us to wait before retrying
+    pub retry_after_ms: Option<u64>,
+    pub remaining_requests: Option<u64>,
+    pub remaining_tokens: Option<u64>,
+    /// When the most restrictive exhausted limit resets
+    pub reset_at: Option<DateTime<Utc>>,
+}
+
+impl RateLimitInfo {
+    /// Parse Anthropic (`anthropic-ratelimit-*`) headers
+    pub fn from_anthropic_headers(headers: &HeaderMap) -> Self {
+        let now = Utc::now();
+        let requests_reset = header_str(headers, "anthropic-ratelimit-requests-reset").and_then(|v| parse_reset(v, now));
+        let tokens_reset = header_str(headers, "anthropic-ratelimit-tokens-reset").and_then(|v| parse_reset(v, now));
+
+        Self {
+            retry_after_ms: header_str(headers, "retry-after").and_then(|v| parse_retry_after(v, now)),
+            remaining_requests: header_u64(headers, "anthropic-ratelimit-requests-remaining"),
+            remaining_tokens: header_u64(headers, "anthropic-ratelimit-tokens-remaining"),
+            reset_at: latest(requests_reset, tokens_reset),
+        }
+    }
+
+    /// Parse OpenRouter / OpenAI-style (`x-ratelimit-*`) headers
+    pub fn from_openrouter_headers(headers: &HeaderMap) -> Self {
+        let now = Utc::now();
+        let reset = header_str(headers, "x-ratelimit-reset")
+            .or_else(|| header_str(headers, "x-ratelimit-reset-requests"))
+            .and_then(|v| parse_reset(v, now));
+        let tokens_reset = header_str(headers, "x-ratelimit-reset-tokens").and_then(|v| parse_reset(v, now));
+
+        Self {
+            retry_after_ms: header_str(headers, "retry-after").and_then(|v| parse_retry_after(v, now)),
+            remaining_requests: header_u64(headers, "x-ratelimit-remaining")
+                .or_else(|| header_u64(headers, "x-ratelimit-remaining-requests")),
+            remaining_tokens: header_u64(headers, "x-ratelimit-remaining-tokens"),
+            reset_at: latest(reset, tokens_reset),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Best estimate of how long to wait before the provider accepts requests again
+    pub fn wait_hint_ms(&self, now: DateTime<Utc>) -> Option<u64> {
+        self.retry_after_ms.or_else(|| {
+            self.reset_at.map(|reset| (reset - now).num_milliseconds().max(0) as u64)
+        })
+    }
+
+    /// Whether the provider reported a limit as fully used
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining_requests == Some(0) || self.remaining_tokens == Some(0)
+    }
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim)
+}
+
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    header_str(headers, name).and_then(|v| v.parse().ok())
+}
+
+fn latest(a: Option<DateTime<Utc>>, b: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// `Retry-After` is either delay seconds or an HTTP date
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<u64> {
+    if let Ok(seconds) = value.parse::<f64>() {
+        return Some((seconds.max(0.0) * 1000.0) as u64);
+    }
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|at| (at.with_timezone(&Utc) - now).num_milliseconds().max(0) as u64)
+}
+
+/// Reset values come as RFC 3339 timestamps, epoch seconds/milliseconds, or durations like `6m0s`
+fn parse_reset(value: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Some(at.with_timezone(&Utc));
+    }
+
+    if let Ok(number) = value.parse::<i64>() {
+        return match number {
+            n if n >= 1_000_000_000_000 => DateTime::from_timestamp_millis(n),
+            n if n >= 1_000_000_000 => DateTime::from_timestamp(n, 0),
+            n => Some(now + chrono::Duration::seconds(n)),
+        };
+    }
+
+    parse_duration(value).map(|d| now + chrono::Duration::milliseconds(d.as_millis() as i64))
+}
+
+/// Parse Go-style durations such as `1s`, `250ms` or `6m0.5s`
+fn parse_duration(value: &str) -> Option<Duration> {
+    let mut total_ms = 0f64;
+    let mut number = String::new();
+    let mut chars = value.chars().peekable();
+    let mut parsed_any = false;
+
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+
+        let amount: f64 = number.parse().ok()?;
+        number.clear();
+        let unit_ms = match c {
+            'h' => 3_600_000.0,
+            'm' if chars.peek() == Some(&'s') => {
+                chars.next();
+                1.0
+            }
+            'm' => 60_000.0,
+            's' => 1_000.0,
+            _ => return None,
+        };
+        total_ms += amount * unit_ms;
+        parsed_any = true;
+    }
+
+    if !number.is_empty() || !parsed_any {
+        return None;
+    }
+    Some(Duration::from_millis(total_ms as u64))
+}
+
+/// Classic token bucket that allows reservations to go into debt; the debt is the wait time
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    available: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn per_minute(limit: u32, now: Instant) -> Self {
+        let capacity = limit.max(1) as f64;
+        Self {
+            capacity,
+            available: capacity,
+            refill_per_sec: capacity / 60.0,
+            last_refill: now,
+        }
+    }
+
+    /// Reserve `amount` units and return how long the caller must wait before using them
+    pub fn reserve(&mut self, amount: f64, now: Instant) -> Duration {
+        self.refill(now);
+        self.available -= amount.min(self.capacity);
+
+        if self.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.available / self.refill_per_sec)
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.available = (self.available + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+}
+
+/// Client-side limiter keeping a provider under its configured RPM/TPM
+#[derive(Debug, Clone)]
+pub struct ClientRateLimiter {
+    requests: Option<TokenBucket>,
+    tokens: Option<TokenBucket>,
+    blocked_until: Option<Instant>,
+}
+
+impl ClientRateLimiter {
+    pub fn new(requests_per_minute: Option<u32>, tokens_per_minute: Option<u32>) -> Self {
+        let now = Instant::now();
+        Self {
+            requests: requests_per_minute.map(|rpm| TokenBucket::per_minute(rpm, now)),
+            tokens: tokens_per_minute.map(|tpm| TokenBucket::per_minute(tpm, now)),
+            blocked_until: None,
+        }
+    }
+
+    /// Reserve one request and `estimated_tokens`, returning the delay before dispatching
+    pub fn reserve(&mut self, estimated_tokens: u32, now: Instant) -> Duration {
+        let request_wait = self.requests.as_mut().map_or(Duration::ZERO, |b| b.reserve(1.0, now));
+        let token_wait = self.tokens.as_mut().map_or(Duration::ZERO, |b| b.reserve(estimated_tokens as f64, now));
+        let blocked_wait = self.blocked_until.map_or(Duration::ZERO, |until| until.saturating_duration_since(now));
+
+        request_wait.max(token_wait).max(blocked_wait)
+    }
+
+    /// Honor limits reported by the provider, pausing dispatch until an exhausted limit resets
+    pub fn observe(&mut self, info: &RateLimitInfo, now: Instant) {
+        if !info.is_exhausted() && info.retry_after_ms.is_none() {
+            return;
+        }
+
+        if let Some(wait_ms) = info.wait_hint_ms(Utc::now()) {
+            let until = now + Duration::from_millis(wait_ms);
+            self.blocked_until = Some(self.blocked_until.map_or(until, |current| current.max(until)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn test_anthropic_headers() {
+        let info = RateLimitInfo::from_anthropic_headers(&headers(&[
+            ("retry-after", "12"),
+            ("anthropic-ratelimit-requests-remaining", "0"),
+            ("anthropic-ratelimit-tokens-remaining", "15000"),
+            ("anthropic-ratelimit-requests-reset", "2030-01-01T00:00:30Z"),
+        ]));
+
+        assert_eq!(info.retry_after_ms, Some(12_000));
+        assert_eq!(info.remaining_requests, Some(0));
+        assert_eq!(info.remaining_tokens, Some(15_000));
+        assert_eq!(info.reset_at, DateTime::parse_from_rfc3339("2030-01-01T00:00:30Z").ok().map(|d| d.with_timezone(&Utc)));
+        assert!(info.is_exhausted());
+    }
+
+    #[test]
+    fn test_openrouter_headers() {
+        let info = RateLimitInfo::from_openrouter_headers(&headers(&[
+            ("x-ratelimit-remaining", "3"),
+            ("x-ratelimit-reset", "1893456000000"),
+        ]));
+
+        assert_eq!(info.retry_after_ms, None);
+        assert_eq!(info.remaining_requests, Some(3));
+        assert_eq!(info.reset_at, DateTime::from_timestamp_millis(1_893_456_000_000));
+        assert!(!info.is_exhausted());
+
+        assert!(RateLimitInfo::from_openrouter_headers(&HeaderMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_parse_duration_formats() {
+        assert_eq!(parse_duration("1s"), Some(Duration::from_secs(1)));
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("soon"), None);
+        assert_eq!(parse_duration("10"), None);
+    }
+
+    #[test]
+    fn test_token_bucket_wait() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::per_minute(60, now);
+
+        for _ in 0..60 {
+            assert_eq!(bucket.reserve(1.0, now), Duration::ZERO);
+        }
+        // One request per second refill rate
+        assert_eq!(bucket.reserve(1.0, now), Duration::from_secs(1));
+        assert_eq!(bucket.reserve(1.0, now + Duration::from_secs(1)), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_limiter_takes_longest_wait_and_observes_server_hints() {
+        let now = Instant::now();
+        let mut limiter = ClientRateLimiter::new(Some(600), Some(6_000));
+
+        assert_eq!(limiter.reserve(6_000, now), Duration::ZERO);
+        // Token bucket is empty: 1000 tokens at 100 tokens/s
+        assert_eq!(limiter.reserve(1_000, now), Duration::from_secs(10));
+
+        let mut unlimited = ClientRateLimiter::new(None, None);
+        unlimited.observe(&RateLimitInfo { retry_after_ms: Some(2_000), ..Default::default() }, now);
+        let wait = unlimited.reserve(1, now);
+        assert!(wait > Duration::from_millis(1_900) && wait <= Duration::from_secs(2));
+    }
+}
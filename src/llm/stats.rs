@@ -0,0 +1,496 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tokio::fs;
+
+use super::router::{RouteLog, RoutePurpose};
+use super::{Provider, TaskType};
+
+/// Aggregated metrics for a group of routed requests
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BreakdownStats {
+    pub requests: u32,
+    pub successful: u32,
+    pub success_rate: f32,
+    pub p50_latency_ms: u64,
+    pub p95_latency_ms: u64,
+    pub tokens: u64,
+    pub cost_cents: f64,
+    pub fallbacks: u32,
+    /// Number of requests keyed by how many retries they needed
+    pub retry_histogram: BTreeMap<u32, u32>,
+    /// Requests answered from the response cache; excluded from latency percentiles
+    #[serde(default)]
+    pub cache_hits: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoutingStats {
+    pub window_start: Option<DateTime<Utc>>,
+    pub total_requests: u32,
+    pub successful_requests: u32,
+    pub failed_requests: u32,
+    pub success_rate: f32,
+    pub provider_usage: HashMap<Provider, u32>,
+    pub average_duration_ms: u64,
+    pub p50_latency_ms: u64,
+    pub p95_latency_ms: u64,
+    pub total_tokens: u64,
+    pub total_cost_cents: f64,
+    pub fallback_count: u32,
+    pub fallback_rate: f32,
+    pub retry_histogram: BTreeMap<u32, u32>,
+    #[serde(default)]
+    pub cache_hits: u32,
+    #[serde(default)]
+    pub cache_hit_rate: f32,
+    pub by_provider: HashMap<Provider, BreakdownStats>,
+    pub by_task_type: HashMap<TaskType, BreakdownStats>,
+    /// Keyed by `id@vN` so template versions can be compared
+    #[serde(default)]
+    pub by_template: HashMap<String, BreakdownStats>,
+}
+
+#[derive(Default)]
+struct Accumulator {
+    requests: u32,
+    successful: u32,
+    durations: Vec<u64>,
+    tokens: u64,
+    cost_cents: f64,
+    fallbacks: u32,
+    retry_histogram: BTreeMap<u32, u32>,
+    cache_hits: u32,
+}
+
+impl Accumulator {
+    fn add(&mut self, entry: &RouteLog) {
+        self.tokens += entry.tokens_used as u64;
+        self.cost_cents += entry.cost_cents.unwrap_or(0.0);
+        if !is_caller_request(entry) {
+            return;
+        }
+
+        self.requests += 1;
+        if entry.success {
+            self.successful += 1;
+        }
+        if is_fallback(entry) {
+            self.fallbacks += 1;
+        }
+        // Cache hits return in microseconds and would hide real provider latency
+        if entry.cache_hit {
+            self.cache_hits += 1;
+        } else {
+            self.durations.push(entry.duration_ms);
+        }
+        *self.retry_histogram.entry(entry.retry_count).or_insert(0) += 1;
+    }
+
+    fn finish(mut self) -> BreakdownStats {
+        self.durations.sort_unstable();
+
+        BreakdownStats {
+            requests: self.requests,
+            successful: self.successful,
+            success_rate: ratio(self.successful, self.requests),
+            p50_latency_ms: percentile(&self.durations, 50.0),
+            p95_latency_ms: percentile(&self.durations, 95.0),
+            tokens: self.tokens,
+            cost_cents: self.cost_cents,
+            fallbacks: self.fallbacks,
+            retry_histogram: self.retry_histogram,
+            cache_hits: self.cache_hits,
+        }
+    }
+}
+
+impl RoutingStats {
+    /// Read the routing log and aggregate every entry at or after `since`
+    pub async fn from_log(log_file_path: impl AsRef<Path>, since: Option<DateTime<Utc>>) -> Result<Self> {
+        let content = match fs::read_to_string(log_file_path.as_ref()).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let entries: Vec<RouteLog> = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str::<RouteLog>(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    log::debug!("Skipping unreadable routing log line: {}", e);
+                    None
+                }
+            })
+            .collect();
+
+        Ok(Self::from_entries(&entries, since))
+    }
+
+    pub fn from_entries(entries: &[RouteLog], since: Option<DateTime<Utc>>) -> Self {
+        let mut overall = Accumulator::default();
+        let mut by_provider: HashMap<Provider, Accumulator> = HashMap::new();
+        let mut by_task_type: HashMap<TaskType, Accumulator> = HashMap::new();
+        let mut by_template: HashMap<String, Accumulator> = HashMap::new();
+
+        let in_window = |entry: &&RouteLog| match since {
+            Some(since) => entry.timestamp >= since,
+            None => true,
+        };
+
+        for entry in entries.iter().filter(in_window) {
+            overall.add(entry);
+            by_provider.entry(attributed_provider(entry)).or_default().add(entry);
+            by_task_type.entry(entry.task_type.clone()).or_default().add(entry);
+            if let Some(template) = &entry.prompt_template {
+                by_template.entry(template.to_string()).or_default().add(entry);
+            }
+        }
+
+        let by_provider: HashMap<Provider, BreakdownStats> = by_provider
+            .into_iter()
+            .map(|(provider, acc)| (provider, acc.finish()))
+            .collect();
+        let by_task_type = by_task_type
+            .into_iter()
+            .map(|(task_type, acc)| (task_type, acc.finish()))
+            .collect();
+        let by_template = by_template
+            .into_iter()
+            .map(|(template, acc)| (template, acc.finish()))
+            .collect();
+
+        let average_duration_ms = if !overall.durations.is_empty() {
+            overall.durations.iter().sum::<u64>() / overall.durations.len() as u64
+        } else {
+            0
+        };
+        let overall = overall.finish();
+
+        Self {
+            window_start: since,
+            total_requests: overall.requests,
+            successful_requests: overall.successful,
+            failed_requests: overall.requests - overall.successful,
+            success_rate: overall.success_rate,
+            provider_usage: by_provider.iter().map(|(p, s)| (p.clone(), s.requests)).collect(),
+            average_duration_ms,
+            p50_latency_ms: overall.p50_latency_ms,
+            p95_latency_ms: overall.p95_latency_ms,
+            total_tokens: overall.tokens,
+            total_cost_cents: overall.cost_cents,
+            fallback_count: overall.fallbacks,
+            fallback_rate: ratio(overall.fallbacks, overall.requests),
+            retry_histogram: overall.retry_histogram,
+            cache_hits: overall.cache_hits,
+            cache_hit_rate: ratio(overall.cache_hits, overall.requests),
+            by_provider,
+            by_task_type,
+            by_template,
+        }
+    }
+
+    /// Plain-text report used by the CLI
+    pub fn format_report(&self) -> String {
+        let mut lines = Vec::new();
+
+        let window = self.window_start
+            .map(|start| format!("since {}", start.format("%Y-%m-%d %H:%M UTC")))
+            .unwrap_or_else(|| "all time".to_string());
+        lines.push(format!("LLM routing stats ({})", window));
+        lines.push(format!(
+            "requests: {} ({} ok, {} failed, {:.1}% success)",
+            self.total_requests, self.successful_requests, self.failed_requests, self.success_rate * 100.0
+        ));
+        lines.push(format!(
+            "latency: avg {}ms, p50 {}ms, p95 {}ms",
+            self.average_duration_ms, self.p50_latency_ms, self.p95_latency_ms
+        ));
+        lines.push(format!("tokens: {}, cost: {:.2}¢", self.total_tokens, self.total_cost_cents));
+        lines.push(format!("fallbacks: {} ({:.1}%)", self.fallback_count, self.fallback_rate * 100.0));
+        lines.push(format!("retries: {}", format_histogram(&self.retry_histogram)));
+        lines.push(format!("cache hits: {} ({:.1}%)", self.cache_hits, self.cache_hit_rate * 100.0));
+
+        let mut providers: Vec<_> = self.by_provider.iter().collect();
+        providers.sort_by_key(|(provider, _)| provider.to_string());
+        lines.push(String::new());
+        lines.push("by provider:".to_string());
+        for (provider, stats) in providers {
+            lines.push(format!("  {:<12} {}", provider.to_string(), format_breakdown(stats)));
+        }
+
+        let mut task_types: Vec<_> = self.by_task_type.iter().collect();
+        task_types.sort_by_key(|(task_type, _)| format!("{:?}", task_type));
+        lines.push(String::new());
+        lines.push("by task type:".to_string());
+        for (task_type, stats) in task_types {
+            lines.push(format!("  {:<12} {}", format!("{:?}", task_type), format_breakdown(stats)));
+        }
+
+        if !self.by_template.is_empty() {
+            let mut templates: Vec<_> = self.by_template.iter().collect();
+            templates.sort_by_key(|(template, _)| template.as_str());
+            lines.push(String::new());
+            lines.push("by prompt template:".to_string());
+            for (template, stats) in templates {
+                lines.push(format!("  {:<12} {}", template, format_breakdown(stats)));
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Successful requests count against the provider that answered, failures against the one first tried
+fn attributed_provider(entry: &RouteLog) -> Provider {
+    if entry.success {
+        entry.final_provider.clone()
+    } else {
+        entry.attempted_provider.clone()
+    }
+}
+
+/// One entry per caller request: losing race contestants and judge, summary or map steps
+/// still add their tokens and cost, but not a request
+fn is_caller_request(entry: &RouteLog) -> bool {
+    entry.purpose == RoutePurpose::Request && entry.race_winner != Some(false)
+}
+
+/// Answered by a candidate after the primary. Race contestants run side by side, so none of
+/// them is a fallback. Entries written before candidates were recorded compare providers.
+fn is_fallback(entry: &RouteLog) -> bool {
+    if !entry.success || entry.race_winner.is_some() {
+        return false;
+    }
+    match entry.candidate_index {
+        Some(index) => index > 0,
+        None => entry.final_provider != entry.attempted_provider,
+    }
+}
+
+fn ratio(part: u32, total: u32) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f32 / total as f32
+    }
+}
+
+/// Nearest-rank percentile over already sorted values
+fn percentile(sorted: &[u64], pct: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn format_histogram(histogram: &BTreeMap<u32, u32>) -> String {
+    if histogram.is_empty() {
+        return "-".to_string();
+    }
+    histogram.iter()
+        .map(|(retries, count)| format!("{}x{}", retries, count))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn format_breakdown(stats: &BreakdownStats) -> String {
+    format!(
+        "{} req, {:.1}% ok, p50 {}ms, p95 {}ms, {} tok, {:.2}¢, {} fallbacks, retries {}",
+        stats.requests,
+        stats.success_rate * 100.0,
+        stats.p50_latency_ms,
+        stats.p95_latency_ms,
+        stats.tokens,
+        stats.cost_cents,
+        stats.fallbacks,
+        format_histogram(&stats.retry_histogram)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    fn entry(task_type: TaskType, attempted: Provider, final_provider: Provider, success: bool, duration_ms: u64, retry_count: u32) -> RouteLog {
+        RouteLog {
+            timestamp: Utc::now(),
+            request_id: Uuid::new_v4(),
+            task_type,
+            attempted_provider: attempted,
+            final_provider,
+            success,
+            duration_ms,
+            error_message: None,
+            retry_count,
+            cost_cents: Some(if success { 3.0 } else { 0.0 }),
+            tokens_used: if success { 100 } else { 0 },
+            model: None,
+            cache_hit: false,
+            prompt_template: None,
+            redactions: Default::default(),
+            race_winner: None,
+            purpose: Default::default(),
+            candidate_index: None,
+        }
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&values, 50.0), 50);
+        assert_eq!(percentile(&values, 95.0), 95);
+        assert_eq!(percentile(&[42], 95.0), 42);
+        assert_eq!(percentile(&[], 50.0), 0);
+    }
+
+    #[test]
+    fn test_aggregation_by_provider_and_task_type() {
+        let entries = vec![
+            entry(TaskType::Plan, Provider::Claude, Provider::Claude, true, 100, 0),
+            entry(TaskType::Plan, Provider::Claude, Provider::OpenRouter, true, 300, 1),
+            entry(TaskType::Review, Provider::Claude, Provider::Offline, false, 900, 3),
+            entry(TaskType::Status, Provider::OpenRouter, Provider::OpenRouter, true, 200, 0),
+        ];
+
+        let stats = RoutingStats::from_entries(&entries, None);
+
+        assert_eq!(stats.total_requests, 4);
+        assert_eq!(stats.successful_requests, 3);
+        assert_eq!(stats.failed_requests, 1);
+        assert_eq!(stats.success_rate, 0.75);
+        assert_eq!(stats.total_tokens, 300);
+        assert_eq!(stats.total_cost_cents, 9.0);
+        assert_eq!(stats.fallback_count, 1);
+        assert_eq!(stats.p50_latency_ms, 200);
+        assert_eq!(stats.p95_latency_ms, 900);
+        assert_eq!(stats.retry_histogram.get(&0), Some(&2));
+        assert_eq!(stats.retry_histogram.get(&3), Some(&1));
+
+        let claude = &stats.by_provider[&Provider::Claude];
+        assert_eq!(claude.requests, 2);
+        assert_eq!(claude.successful, 1);
+
+        let openrouter = &stats.by_provider[&Provider::OpenRouter];
+        assert_eq!(openrouter.requests, 2);
+        assert_eq!(openrouter.fallbacks, 1);
+
+        let plan = &stats.by_task_type[&TaskType::Plan];
+        assert_eq!(plan.requests, 2);
+        assert_eq!(plan.success_rate, 1.0);
+        assert_eq!(plan.tokens, 200);
+    }
+
+    #[test]
+    fn test_fallback_within_one_provider() {
+        let mut primary = entry(TaskType::Plan, Provider::Claude, Provider::Claude, true, 100, 0);
+        primary.candidate_index = Some(0);
+        let mut second_model = entry(TaskType::Plan, Provider::Claude, Provider::Claude, true, 100, 2);
+        second_model.candidate_index = Some(1);
+        let mut race_contestant = entry(TaskType::Plan, Provider::Claude, Provider::OpenRouter, true, 100, 0);
+        race_contestant.candidate_index = Some(1);
+        race_contestant.race_winner = Some(true);
+
+        let stats = RoutingStats::from_entries(&[primary, second_model, race_contestant], None);
+        assert_eq!(stats.fallback_count, 1);
+    }
+
+    #[test]
+    fn test_losing_contestants_and_helper_calls_are_not_requests() {
+        let mut winner = entry(TaskType::Apply, Provider::Claude, Provider::Claude, true, 100, 0);
+        winner.race_winner = Some(true);
+        let mut loser = entry(TaskType::Apply, Provider::OpenRouter, Provider::OpenRouter, true, 900, 0);
+        loser.race_winner = Some(false);
+        let mut judge = entry(TaskType::Apply, Provider::Claude, Provider::Claude, true, 50, 0);
+        judge.purpose = RoutePurpose::Judge;
+        let mut summary = entry(TaskType::Apply, Provider::Claude, Provider::Claude, false, 50, 0);
+        summary.purpose = RoutePurpose::ContextFit;
+
+        let stats = RoutingStats::from_entries(&[winner, loser, judge, summary], None);
+        assert_eq!(stats.total_requests, 1);
+        assert_eq!(stats.failed_requests, 0);
+        assert_eq!(stats.p95_latency_ms, 100);
+        assert_eq!(stats.total_tokens, 300);
+        assert!((stats.total_cost_cents - 9.0).abs() < 1e-9);
+        assert_eq!(stats.by_provider[&Provider::OpenRouter].requests, 0);
+        assert!((stats.by_provider[&Provider::OpenRouter].cost_cents - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_window_excludes_old_entries() {
+        let mut old = entry(TaskType::Plan, Provider::Claude, Provider::Claude, true, 100, 0);
+        old.timestamp = Utc::now() - chrono::Duration::days(2);
+        let recent = entry(TaskType::Plan, Provider::Claude, Provider::Claude, true, 100, 0);
+
+        let stats = RoutingStats::from_entries(&[old, recent], Some(Utc::now() - chrono::Duration::days(1)));
+        assert_eq!(stats.total_requests, 1);
+    }
+
+    #[test]
+    fn test_cache_hits_excluded_from_latency() {
+        let mut hit = entry(TaskType::Review, Provider::Claude, Provider::Claude, true, 1, 0);
+        hit.cache_hit = true;
+        hit.tokens_used = 0;
+        hit.cost_cents = None;
+        let miss = entry(TaskType::Review, Provider::Claude, Provider::Claude, true, 800, 0);
+
+        let stats = RoutingStats::from_entries(&[hit, miss], None);
+        assert_eq!(stats.cache_hits, 1);
+        assert_eq!(stats.cache_hit_rate, 0.5);
+        assert_eq!(stats.p50_latency_ms, 800);
+        assert_eq!(stats.average_duration_ms, 800);
+        assert_eq!(stats.total_cost_cents, 3.0);
+        assert_eq!(stats.by_task_type[&TaskType::Review].cache_hits, 1);
+    }
+
+    #[test]
+    fn test_breakdown_by_template_version() {
+        let versioned = |version: u32, success: bool| {
+            let mut e = entry(TaskType::Plan, Provider::Claude, Provider::Claude, success, 100, 0);
+            e.prompt_template = Some(crate::llm::PromptRef { id: "plan".to_string(), version });
+            e
+        };
+        let entries = vec![
+            versioned(1, true),
+            versioned(1, false),
+            versioned(2, true),
+            entry(TaskType::Plan, Provider::Claude, Provider::Claude, true, 100, 0),
+        ];
+
+        let stats = RoutingStats::from_entries(&entries, None);
+        assert_eq!(stats.by_template.len(), 2);
+        assert_eq!(stats.by_template["plan@v1"].success_rate, 0.5);
+        assert_eq!(stats.by_template["plan@v2"].requests, 1);
+        assert!(stats.format_report().contains("plan@v2"));
+    }
+
+    #[tokio::test]
+    async fn test_from_log_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("log.jsonl");
+
+        let lines = [
+            entry(TaskType::Review, Provider::Claude, Provider::Claude, true, 120, 0),
+            entry(TaskType::Review, Provider::Claude, Provider::Claude, false, 80, 3),
+        ]
+        .iter()
+        .map(|e| serde_json::to_string(e).unwrap())
+        .collect::<Vec<_>>()
+        .join("\n");
+        fs::write(&log_path, format!("{}\ngarbage\n", lines)).await.unwrap();
+
+        let stats = RoutingStats::from_log(&log_path, None).await.unwrap();
+        assert_eq!(stats.total_requests, 2);
+        assert!(stats.format_report().contains("Review"));
+
+        let missing = RoutingStats::from_log(temp_dir.path().join("missing.jsonl"), None).await.unwrap();
+        assert_eq!(missing.total_requests, 0);
+    }
+}
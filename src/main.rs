@@ -1,5 +1,6 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use log::info;
+use serde::Deserialize;
 use std::env;
 
 mod orchestrator;
@@ -7,15 +8,44 @@ mod desktop;
 mod llm;
 mod tui;
 mod workflows;
+mod cli;
 
+use desktop::{TerminalConfig, TerminalController};
 use orchestrator::{Orchestrator, OrchestratorConfig};
 use tui::App;
 
+/// The parts of config.yaml read at startup; other sections keep their defaults for now
+#[derive(Debug, Default, Deserialize)]
+struct FileConfig {
+    #[serde(default)]
+    desktop: DesktopConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DesktopConfig {
+    #[serde(default)]
+    terminal: TerminalConfig,
+}
+
+impl FileConfig {
+    fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        serde_yaml::from_str(&content)
+            .map_err(|e| anyhow!("Invalid configuration in {}: {}", path, e))
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize logging
     env_logger::init();
     
+    // Handle CLI subcommands before starting the TUI
+    let args: Vec<String> = env::args().skip(1).collect();
+    if cli::run(&args).await? {
+        return Ok(());
+    }
+    
     // Load configuration
     let config_path = env::var("CONFIG_PATH").unwrap_or_else(|_| "config.yaml".to_string());
     
@@ -23,21 +53,25 @@ async fn main() -> Result<()> {
     info!("Loading configuration from: {}", config_path);
     
     // Initialize orchestrator with config loading
-    let config = match std::path::Path::new(&config_path).exists() {
+    let file_config = match std::path::Path::new(&config_path).exists() {
         true => {
             info!("Loading configuration from: {}", config_path);
-            // For now, use default config but in production this would parse config.yaml
-            OrchestratorConfig::default()
+            FileConfig::load(&config_path)?
         }
         false => {
             info!("Configuration file not found, using defaults");
-            OrchestratorConfig::default()
+            FileConfig::default()
         }
     };
+    let config = OrchestratorConfig::default();
     let orchestrator = Orchestrator::new(config).await?;
     
+    // Commands run from the repository the agent was started in and are audited with the run
+    let terminal = TerminalController::from_config(&file_config.desktop.terminal, env::current_dir()?)?
+        .with_audit_log(orchestrator.command_audit_log());
+    
     // Start TUI application
-    let mut app = App::new(orchestrator);
+    let mut app = App::new(orchestrator).with_terminal(terminal);
     app.run().await?;
     
     info!("DeskAgent shutdown complete");
@@ -7,6 +7,7 @@ use tokio::io::AsyncWriteExt;
 use uuid::Uuid;
 
 use super::task::Task;
+use crate::desktop::audit::{CommandAuditEntry, COMMAND_AUDIT_FILE};
 use super::state::TaskState;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +28,8 @@ pub enum EventType {
     TaskCancelled,
     TaskRetried,
     StateTransition,
+    /// A terminal command ran for the task; details point at its `commands.jsonl` entry
+    CommandExecuted,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -91,7 +94,8 @@ impl EventLogger {
         Ok(())
     }
     
-    fn get_session_directory(&self) -> PathBuf {
+    /// Directory holding this run's artifacts
+    pub fn get_session_directory(&self) -> PathBuf {
         let timestamp = self.current_session.start_time.format("%Y%m%d_%H%M%S");
         self.base_dir.join(format!("{}_{}", timestamp, &self.current_session.session_id.to_string()[..8]))
     }
@@ -188,6 +192,25 @@ impl EventLogger {
         self.log_event(event).await
     }
     
+    pub async fn log_command_executed(&mut self, entry: &CommandAuditEntry) -> Result<()> {
+        let Some(task_id) = entry.task_id else { return Ok(()) };
+        let event = TaskEvent {
+            event_id: Uuid::new_v4(),
+            task_id,
+            event_type: EventType::CommandExecuted,
+            timestamp: entry.timestamp,
+            details: serde_json::json!({
+                "audit_log": COMMAND_AUDIT_FILE,
+                "audit_entry": entry.entry_id,
+                "command": entry.command,
+                "exit_code": entry.exit_code,
+                "policy_allowed": entry.policy.allowed
+            }),
+        };
+        
+        self.log_event(event).await
+    }
+    
     pub async fn finalize_session(&mut self) -> Result<()> {
         self.current_session.end_time = Some(Utc::now());
         
@@ -215,6 +238,8 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
     use crate::orchestrator::task::{Task, TaskType};
+    use crate::desktop::audit::CommandAuditLog;
+    use crate::desktop::{CommandPolicy, PolicyContext};
     
     #[tokio::test]
     async fn test_event_logger_creation() {
@@ -253,6 +278,34 @@ mod tests {
         assert!(log_file.exists());
     }
     
+    #[tokio::test]
+    async fn test_command_events_reference_audit_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut logger = EventLogger::new(temp_dir.path()).await.unwrap();
+        let audit = CommandAuditLog::new(logger.get_session_directory().join(COMMAND_AUDIT_FILE));
+        let task_id = Uuid::new_v4();
+        let context = PolicyContext::new(temp_dir.path()).with_task_id(task_id);
+        
+        let decision = CommandPolicy::default().evaluate("cargo test", &context);
+        let entry = audit.entry("cargo test", &context, &decision);
+        audit.append(&entry).await.unwrap();
+        let untracked = audit.entry("ls", &PolicyContext::new(temp_dir.path()), &decision);
+        audit.append(&untracked).await.unwrap();
+        
+        for entry in audit.take_unreported() {
+            logger.log_command_executed(&entry).await.unwrap();
+        }
+        
+        assert_eq!(logger.get_events().len(), 1);
+        let event = &logger.get_events()[0];
+        assert_eq!(event.task_id, task_id);
+        assert!(matches!(event.event_type, EventType::CommandExecuted));
+        assert_eq!(event.details["audit_entry"], serde_json::json!(entry.entry_id));
+        let audited = CommandAuditLog::read_entries(audit.path()).await.unwrap();
+        assert_eq!(audited.len(), 2);
+        assert_eq!(audited[0].entry_id, entry.entry_id);
+    }
+    
     #[tokio::test]
     async fn test_session_finalization() {
         let temp_dir = TempDir::new().unwrap();
@@ -6,12 +6,14 @@ use anyhow::Result;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
 use uuid::Uuid;
 
 pub use task::{Task, TaskType, TaskRequest, TaskAction};
 pub use state::{TaskState, StateManager};
 use logger::EventLogger;
+use crate::desktop::audit::{CommandAuditLog, COMMAND_AUDIT_FILE};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrchestratorConfig {
@@ -35,6 +37,7 @@ pub struct Orchestrator {
     tasks: RwLock<HashMap<Uuid, Task>>,
     state_manager: StateManager,
     event_logger: EventLogger,
+    command_audit: Arc<CommandAuditLog>,
     config: OrchestratorConfig,
     task_sender: mpsc::UnboundedSender<TaskRequest>,
     task_receiver: RwLock<Option<mpsc::UnboundedReceiver<TaskRequest>>>,
@@ -44,12 +47,14 @@ impl Orchestrator {
     pub async fn new(config: OrchestratorConfig) -> Result<Self> {
         let state_manager = StateManager::new();
         let event_logger = EventLogger::new(&config.log_directory).await?;
+        let command_audit = Arc::new(CommandAuditLog::new(event_logger.get_session_directory().join(COMMAND_AUDIT_FILE)));
         let (task_sender, task_receiver) = mpsc::unbounded_channel();
         
         Ok(Self {
             tasks: RwLock::new(HashMap::new()),
             state_manager,
             event_logger,
+            command_audit,
             config,
             task_sender,
             task_receiver: RwLock::new(Some(task_receiver)),
@@ -106,6 +111,25 @@ impl Orchestrator {
         Ok(())
     }
     
+    /// Directory where artifacts of the current run are stored
+    pub fn run_directory(&self) -> std::path::PathBuf {
+        self.event_logger.get_session_directory()
+    }
+    
+    /// Audit log for terminal commands of this run, `runs/<session>/commands.jsonl`
+    pub fn command_audit_log(&self) -> Arc<CommandAuditLog> {
+        self.command_audit.clone()
+    }
+    
+    /// Log a `CommandExecuted` event for each task command audited since the last call
+    pub async fn sync_command_events(&mut self) -> Result<usize> {
+        let entries = self.command_audit.take_unreported();
+        for entry in &entries {
+            self.event_logger.log_command_executed(entry).await?;
+        }
+        Ok(entries.len())
+    }
+    
     pub async fn start_processing(&self) -> Result<()> {
         let mut receiver = {
             let mut receiver_lock = self.task_receiver.write().await;
@@ -148,12 +172,14 @@ impl Orchestrator {
     pub fn new_sync(config: OrchestratorConfig) -> Self {
         let state_manager = StateManager::new();
         let event_logger = EventLogger::new_sync(&config.log_directory);
+        let command_audit = Arc::new(CommandAuditLog::new(event_logger.get_session_directory().join(COMMAND_AUDIT_FILE)));
         let (task_sender, task_receiver) = mpsc::unbounded_channel();
         
         Self {
             tasks: RwLock::new(HashMap::new()),
             state_manager,
             event_logger,
+            command_audit,
             config,
             task_sender,
             task_receiver: RwLock::new(Some(task_receiver)),
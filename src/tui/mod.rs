@@ -16,14 +16,25 @@ use ratatui::{
     Frame, Terminal,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::io;
 use std::path::Path;
 use std::time::{Duration, Instant};
 use tokio::fs;
 use uuid::Uuid;
 
+use crate::desktop::terminal::{CommandStream, OutputEvent};
+use crate::desktop::TerminalController;
+use crate::llm::{health, CircuitState, ProviderHealthSnapshot, RoutingStats};
 use crate::orchestrator::{Orchestrator, TaskState};
 
+/// Routing log read for the LLM statistics shown in the status panel
+const ROUTING_LOG_PATH: &str = "routing/log.jsonl";
+/// Provider health snapshots written by the LLM router
+const PROVIDER_HEALTH_PATH: &str = "routing/health.json";
+/// Output lines kept for the live command panel
+const LIVE_OUTPUT_LINES: usize = 200;
+
 #[derive(Debug)]
 pub struct App {
     pub orchestrator: Orchestrator,
@@ -37,6 +48,12 @@ pub struct App {
     pub pending_action: Option<PendingAction>,
     pub last_refresh: Instant,
     pub loading: bool,
+    pub routing_stats: Option<RoutingStats>,
+    pub provider_health: Vec<ProviderHealthSnapshot>,
+    pub live_output: VecDeque<OutputEvent>,
+    pub live_command: Option<(String, CommandStream)>,
+    /// Runs live commands; built from `desktop.terminal` in config.yaml
+    pub terminal: TerminalController,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,9 +89,20 @@ impl App {
             pending_action: None,
             last_refresh: Instant::now(),
             loading: false,
+            routing_stats: None,
+            provider_health: Vec::new(),
+            live_output: VecDeque::new(),
+            live_command: None,
+            terminal: TerminalController::new(),
         }
     }
     
+    /// Run live commands with `terminal`'s policy and sandbox instead of the defaults
+    pub fn with_terminal(mut self, terminal: TerminalController) -> Self {
+        self.terminal = terminal;
+        self
+    }
+    
     pub async fn run(&mut self) -> Result<()> {
         // Initialize terminal
         enable_raw_mode()?;
@@ -146,6 +174,12 @@ impl App {
                             KeyCode::Esc if self.show_confirmation => {
                                 self.cancel_action();
                             }
+                            KeyCode::Char('b') | KeyCode::Char('B') => {
+                                self.start_live_command("Build", "cargo build");
+                            }
+                            KeyCode::Char('t') | KeyCode::Char('T') => {
+                                self.start_live_command("Test", "cargo test");
+                            }
                             KeyCode::F(5) => {
                                 self.refresh_data().await?;
                             }
@@ -156,6 +190,8 @@ impl App {
             }
             
             if last_tick.elapsed() >= tick_rate {
+                self.poll_live_output().await;
+                
                 // Auto-refresh every 30 seconds
                 if self.last_refresh.elapsed() >= Duration::from_secs(30) {
                     self.refresh_data().await?;
@@ -194,8 +230,17 @@ impl App {
             .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
             .split(chunks[1]);
         
-        // Left panel - Recent tasks
-        self.render_tasks_panel(f, main_chunks[0]);
+        // Left panel - Recent tasks, with live command output below once a command ran
+        if self.live_output.is_empty() && self.live_command.is_none() {
+            self.render_tasks_panel(f, main_chunks[0]);
+        } else {
+            let left_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .split(main_chunks[0]);
+            self.render_tasks_panel(f, left_chunks[0]);
+            self.render_live_output(f, left_chunks[1]);
+        }
         
         // Right panel - Status and controls
         self.render_status_panel(f, main_chunks[1]);
@@ -268,6 +313,28 @@ impl App {
         f.render_widget(tasks_list, area);
     }
     
+    fn render_live_output(&self, f: &mut Frame, area: Rect) {
+        let title = match &self.live_command {
+            Some((label, _)) => format!("{} (running)", label),
+            None => "Command Output".to_string(),
+        };
+        
+        // Show the tail that fits inside the borders
+        let visible = area.height.saturating_sub(2) as usize;
+        let lines: Vec<Line> = self.live_output
+            .iter()
+            .skip(self.live_output.len().saturating_sub(visible))
+            .map(|event| {
+                let color = if event.is_stderr() { Color::Red } else { Color::White };
+                Line::from(Span::styled(event.line().to_string(), Style::default().fg(color)))
+            })
+            .collect();
+        
+        let output = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(output, area);
+    }
+    
     fn render_status_panel(&self, f: &mut Frame, area: Rect) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -310,6 +377,8 @@ impl App {
             ("S", "Status"),
             ("F", "Follow-up"),
             ("A", "Apply"),
+            ("B", "Build"),
+            ("T", "Test"),
             ("F5", "Refresh"),
             ("Q", "Quit"),
         ];
@@ -368,6 +437,20 @@ impl App {
         // Load recent task summaries
         self.recent_tasks = self.load_recent_tasks().await?;
         
+        // Load LLM routing statistics for the last 24 hours
+        let since = Utc::now() - chrono::Duration::hours(24);
+        self.routing_stats = match RoutingStats::from_log(ROUTING_LOG_PATH, Some(since)).await {
+            Ok(stats) => Some(stats),
+            Err(e) => {
+                log::warn!("Failed to load routing stats: {}", e);
+                None
+            }
+        };
+        self.provider_health = health::read_snapshots(PROVIDER_HEALTH_PATH).await.unwrap_or_else(|e| {
+            log::warn!("Failed to load provider health: {}", e);
+            Vec::new()
+        });
+        
         self.loading = false;
         Ok(())
     }
@@ -468,7 +551,7 @@ impl App {
     }
     
     pub fn generate_summary_text(&self) -> Text {
-        let lines = vec![
+        let mut lines = vec![
             Line::from("Modules: M1 ✅, M2 ✅, M3 ✅"),
             Line::from("M4: TUI Dashboard (In Progress)"),
             Line::from("M5: PLAN & REVIEW (Pending)"),
@@ -476,9 +559,60 @@ impl App {
             Line::from(format!("Tasks: {} recent", self.recent_tasks.len())),
             Line::from("Tests: 33/33 passing"),
         ];
+        lines.extend(self.routing_stats_lines());
+        lines.extend(self.provider_health_lines());
         Text::from(lines)
     }
     
+    fn provider_health_lines(&self) -> Vec<Line<'static>> {
+        if self.provider_health.is_empty() {
+            return Vec::new();
+        }
+        
+        let mut lines = vec![Line::from(""), Line::from("Providers:")];
+        for snapshot in &self.provider_health {
+            let icon = match snapshot.state {
+                CircuitState::Closed => "🟢",
+                CircuitState::HalfOpen => "🟡",
+                CircuitState::Open => "🔴",
+            };
+            lines.push(Line::from(format!(
+                "  {} {} {} ({:.0}% err)",
+                icon, snapshot.provider, snapshot.state, snapshot.error_rate * 100.0
+            )));
+        }
+        lines
+    }
+    
+    fn routing_stats_lines(&self) -> Vec<Line<'static>> {
+        let Some(stats) = &self.routing_stats else {
+            return Vec::new();
+        };
+        
+        let mut lines = vec![
+            Line::from(""),
+            Line::from(format!(
+                "LLM 24h: {} req, {:.0}% ok, {:.1}¢",
+                stats.total_requests, stats.success_rate * 100.0, stats.total_cost_cents
+            )),
+            Line::from(format!(
+                "Latency p50/p95: {}/{}ms, fallbacks {}",
+                stats.p50_latency_ms, stats.p95_latency_ms, stats.fallback_count
+            )),
+        ];
+        
+        let mut task_types: Vec<_> = stats.by_task_type.iter().collect();
+        task_types.sort_by_key(|(task_type, _)| format!("{:?}", task_type));
+        for (task_type, breakdown) in task_types {
+            lines.push(Line::from(format!(
+                "  {:?}: {} req, {} tok, {:.1}¢",
+                task_type, breakdown.requests, breakdown.tokens, breakdown.cost_cents
+            )));
+        }
+        
+        lines
+    }
+    
     pub async fn handle_plan_action(&mut self) {
         if self.is_high_risk_operation("PLAN") {
             self.show_confirmation_dialog(
@@ -660,6 +794,46 @@ impl App {
         self.status_message = "FOLLOWUP operation completed successfully".to_string();
     }
     
+    /// Run `command` in the background and stream its output into the live panel
+    pub fn start_live_command(&mut self, label: &str, command: &str) {
+        if let Some((running, _)) = &self.live_command {
+            self.status_message = format!("{} is still running", running);
+            return;
+        }
+        
+        match self.terminal.stream_command_with_timeout(command, self.terminal.tool_timeout()) {
+            Ok(stream) => {
+                self.live_output.clear();
+                self.live_command = Some((label.to_string(), stream));
+                self.status_message = format!("{} running: {}", label, command);
+            }
+            Err(e) => self.status_message = format!("{} failed to start: {}", label, e),
+        }
+    }
+    
+    /// Move buffered output into the live panel and report the result once the command exits
+    pub async fn poll_live_output(&mut self) {
+        let Some((_, stream)) = self.live_command.as_mut() else { return };
+        
+        while let Some(event) = stream.try_next_event() {
+            if self.live_output.len() == LIVE_OUTPUT_LINES {
+                self.live_output.pop_front();
+            }
+            self.live_output.push_back(event);
+        }
+        if !stream.is_finished() {
+            return;
+        }
+        
+        let Some((label, stream)) = self.live_command.take() else { return };
+        self.status_message = match stream.finish().await {
+            Ok(result) if result.timed_out => format!("{} timed out after {}ms", label, result.duration_ms),
+            Ok(result) if result.exit_code == 0 => format!("{} succeeded in {}ms", label, result.duration_ms),
+            Ok(result) => format!("{} failed with exit code {}", label, result.exit_code),
+            Err(e) => format!("{} failed: {}", label, e),
+        };
+    }
+    
     pub fn is_high_risk_operation(&self, operation: &str) -> bool {
         // Consider APPLY as high-risk, others as medium-risk requiring confirmation
         matches!(operation, "APPLY" | "PLAN" | "REVIEW" | "FOLLOWUP")
@@ -770,6 +944,44 @@ mod tests {
         assert!(app.should_quit);
     }
 
+    #[tokio::test]
+    async fn test_summary_includes_routing_stats() {
+        let config = OrchestratorConfig::default();
+        let orchestrator = Orchestrator::new(config).await.unwrap();
+        let mut app = App::new(orchestrator);
+        
+        let without_stats = app.generate_summary_text().lines.len();
+        
+        app.routing_stats = Some(RoutingStats::from_entries(&[], None));
+        let with_stats = app.generate_summary_text();
+        
+        assert!(with_stats.lines.len() > without_stats);
+        assert!(with_stats.lines.iter().any(|line| line.to_string().contains("LLM 24h: 0 req")));
+    }
+
+    #[tokio::test]
+    async fn test_live_command_output() {
+        let config = OrchestratorConfig::default();
+        let orchestrator = Orchestrator::new(config).await.unwrap();
+        let mut app = App::new(orchestrator);
+        
+        app.start_live_command("Build", "echo compiling; echo 'warning: unused' >&2; exit 3");
+        assert!(app.live_command.is_some());
+        
+        for _ in 0..50 {
+            app.poll_live_output().await;
+            if app.live_command.is_none() {
+                break;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        
+        assert!(app.live_command.is_none());
+        assert_eq!(app.live_output.len(), 2);
+        assert!(app.live_output.iter().any(|event| event.is_stderr() && event.line() == "warning: unused"));
+        assert_eq!(app.status_message, "Build failed with exit code 3");
+    }
+    
     #[test]
     fn test_status_message_handling() {
         let config = OrchestratorConfig::default();
@@ -381,6 +381,36 @@ mod tests {
         assert!(std::ptr::eq(workflow.cursor, &cursor));
     }
 
+    #[tokio::test]
+    async fn test_execute_with_recording_backend() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let notes = temp_dir.path().join("NOTES.md");
+        std::fs::write(&notes, "# Notes\n").unwrap();
+        let new_file = temp_dir.path().join("src/feature.rs");
+
+        let backend = std::sync::Arc::new(crate::desktop::RecordingBackend::new());
+        let cursor = CursorController::new().with_backend(backend.clone());
+        let workflow = EditWorkflow::new(&cursor);
+        let plan_data = serde_json::json!({
+            "tasks": [{
+                "task_id": "add-feature",
+                "title": "Add Feature",
+                "description": "Feature description",
+                "file_targets": [notes.to_string_lossy(), new_file.to_string_lossy()],
+                "task_type": "Implementation"
+            }]
+        });
+
+        let result = workflow.execute(plan_data).await.unwrap();
+
+        assert_eq!(result["success"], true);
+        assert_eq!(result["cursor_interactions"][1]["success"], true);
+        let notes_content = std::fs::read_to_string(&notes).unwrap();
+        assert!(notes_content.starts_with("# Notes\n## Add Feature"));
+        assert!(std::fs::read_to_string(&new_file).unwrap().contains("AddFeaturePlaceholder"));
+        assert!(!backend.actions().is_empty());
+    }
+
     #[test]
     fn test_to_pascal_case() {
         assert_eq!(to_pascal_case("test-task"), "TestTask");
@@ -1,20 +1,23 @@
 pub mod plan;
 pub mod edit;
 pub mod review;
+pub mod repo_index;
 
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
-pub use plan::PlanWorkflow;
+pub use plan::{PlanWorkflow, TaskPlan};
 pub use edit::EditWorkflow;
-pub use review::ReviewWorkflow;
+pub use review::{ReviewResult, ReviewWorkflow};
+pub use repo_index::RepoIndex;
 
 use crate::orchestrator::Orchestrator;
 use crate::desktop::{CursorController, TerminalController};
-use crate::llm::LlmRouter;
+use crate::llm::conversation::CONVERSATION_FILE;
+use crate::llm::{Conversation, LlmRouter, PromptLibrary, TaskType};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum WorkflowType {
@@ -53,19 +56,31 @@ pub struct WorkflowManager {
     terminal: TerminalController,
     llm: LlmRouter,
     base_path: PathBuf,
+    conversation: Conversation,
+    /// Send screenshots and plan diagrams with REVIEW requests
+    review_images: bool,
 }
 
 impl WorkflowManager {
     pub fn new(orchestrator: Orchestrator, cursor: CursorController, terminal: TerminalController, llm: LlmRouter, base_path: PathBuf) -> Self {
+        // Commands run by any workflow are audited in this run's directory
+        let terminal = terminal.with_audit_log(orchestrator.command_audit_log());
         Self {
             orchestrator,
             cursor,
             terminal,
             llm,
             base_path,
+            conversation: Conversation::new(),
+            review_images: false,
         }
     }
 
+    pub fn with_review_images(mut self, enabled: bool) -> Self {
+        self.review_images = enabled;
+        self
+    }
+
     pub async fn execute_plan_workflow(&mut self, sprint_file: PathBuf) -> Result<WorkflowResult> {
         let workflow_id = Uuid::new_v4();
         let mut result = WorkflowResult {
@@ -88,6 +103,11 @@ impl WorkflowManager {
                 result.completed_at = Some(Utc::now());
                 result.output_data = Some(plan_data.clone());
                 result.artifacts.push(self.base_path.join("plans/sprint-01.plan.json"));
+
+                if let Ok(plan) = serde_json::from_value::<TaskPlan>(plan_data) {
+                    self.conversation.record(TaskType::Plan, format!("Plan the sprint in {}", plan.sprint_file), plan.conversation_digest());
+                    result.artifacts.push(self.save_conversation().await?);
+                }
             }
             Err(e) => {
                 result.status = WorkflowStatus::Failed;
@@ -95,6 +115,7 @@ impl WorkflowManager {
                 result.error_message = Some(e.to_string());
             }
         }
+        self.orchestrator.sync_command_events().await?;
 
         Ok(result)
     }
@@ -127,6 +148,7 @@ impl WorkflowManager {
                 result.error_message = Some(e.to_string());
             }
         }
+        self.orchestrator.sync_command_events().await?;
 
         Ok(result)
     }
@@ -145,14 +167,57 @@ impl WorkflowManager {
             artifacts: Vec::new(),
         };
 
-        let review_workflow = ReviewWorkflow::new(&self.llm, &self.base_path);
+        let review_workflow = ReviewWorkflow::new(&self.llm, &self.terminal, &self.base_path)
+            .with_task_id(workflow_id)
+            .with_image_attachments(self.review_images);
         
         match review_workflow.execute().await {
             Ok(review_data) => {
                 result.status = WorkflowStatus::Completed;
                 result.completed_at = Some(Utc::now());
+                if let Ok(review) = serde_json::from_value::<ReviewResult>(review_data.clone()) {
+                    self.conversation.record(TaskType::Review, "Review the changes made for this sprint", review.conversation_digest());
+                }
                 result.output_data = Some(review_data);
                 result.artifacts.push(self.base_path.join("reviews/AI_REVIEW.md"));
+                result.artifacts.push(self.save_conversation().await?);
+            }
+            Err(e) => {
+                result.status = WorkflowStatus::Failed;
+                result.completed_at = Some(Utc::now());
+                result.error_message = Some(e.to_string());
+            }
+        }
+        self.orchestrator.sync_command_events().await?;
+
+        Ok(result)
+    }
+
+    /// Ask a question about this run, answered with the PLAN and REVIEW history as context
+    pub async fn execute_followup_workflow(&mut self, question: String) -> Result<WorkflowResult> {
+        let workflow_id = Uuid::new_v4();
+        let mut result = WorkflowResult {
+            workflow_id,
+            workflow_type: WorkflowType::Followup,
+            status: WorkflowStatus::Running,
+            started_at: Utc::now(),
+            completed_at: None,
+            input_data: serde_json::json!({"question": question}),
+            output_data: None,
+            error_message: None,
+            artifacts: Vec::new(),
+        };
+
+        let prompts = PromptLibrary::load(self.base_path.join("prompts")).await?;
+
+        match self.conversation.send(&self.llm, &prompts, TaskType::Followup, question).await {
+            Ok(response) => {
+                result.status = WorkflowStatus::Completed;
+                result.completed_at = Some(Utc::now());
+                result.output_data = Some(serde_json::json!({
+                    "answer": response.content,
+                    "conversation_id": self.conversation.id,
+                }));
             }
             Err(e) => {
                 result.status = WorkflowStatus::Failed;
@@ -160,6 +225,8 @@ impl WorkflowManager {
                 result.error_message = Some(e.to_string());
             }
         }
+        result.artifacts.push(self.save_conversation().await?);
+        self.orchestrator.sync_command_events().await?;
 
         Ok(result)
     }
@@ -188,6 +255,21 @@ impl WorkflowManager {
     pub fn get_base_path(&self) -> &PathBuf {
         &self.base_path
     }
+
+    pub fn get_conversation(&self) -> &Conversation {
+        &self.conversation
+    }
+
+    /// Continue the conversation stored with an earlier run, e.g. to ask a FOLLOWUP about its review
+    pub async fn resume_conversation(&mut self, run_dir: &Path) {
+        self.conversation = Conversation::load_or_new(run_dir).await;
+    }
+
+    async fn save_conversation(&self) -> Result<PathBuf> {
+        let path = self.orchestrator.run_directory().join(CONVERSATION_FILE);
+        self.conversation.save(&path).await?;
+        Ok(path)
+    }
 }
 
 impl WorkflowManager {
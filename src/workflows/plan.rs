@@ -5,7 +5,11 @@ use std::path::PathBuf;
 use tokio::fs;
 use uuid::Uuid;
 
-use crate::llm::LlmRouter;
+use crate::llm::{LlmRouter, PromptLibrary, RenderedPrompt};
+use super::repo_index;
+
+/// Repository chunks retrieved for the sprint document
+const RETRIEVED_CHUNKS: usize = 6;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskPlan {
@@ -47,6 +51,17 @@ pub enum TaskPriority {
     Critical,
 }
 
+impl TaskPlan {
+    /// Short description of the plan kept in the run's conversation history
+    pub fn conversation_digest(&self) -> String {
+        let tasks = self.tasks.iter()
+            .map(|t| format!("- {}: {}", t.task_id, t.title))
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!("Plan for {}: {}\nTasks:\n{}", self.sprint_file, self.overview, tasks)
+    }
+}
+
 pub struct PlanWorkflow<'a> {
     llm: &'a LlmRouter,
     base_path: &'a PathBuf,
@@ -78,11 +93,14 @@ impl<'a> PlanWorkflow<'a> {
     }
 
     async fn generate_plan_with_llm(&self, sprint_content: &str, sprint_file: &PathBuf) -> Result<TaskPlan> {
-        let prompt = self.create_planning_prompt(sprint_content);
+        let prompts = PromptLibrary::load(self.base_path.join("prompts")).await?;
+        let prompt = self.create_planning_prompt(&prompts, sprint_content)?;
 
         // Create LLM request
-        let messages = vec![crate::llm::Message::user(prompt)];
-        let request = crate::llm::LlmRequest::new(crate::llm::TaskType::Plan, messages);
+        let mut request = crate::llm::LlmRequest::from_prompt(crate::llm::TaskType::Plan, prompt);
+        if let Some(message) = request.messages.last_mut() {
+            message.attachments.extend(repo_index::relevant_context(self.llm, self.base_path, sprint_content, RETRIEVED_CHUNKS).await);
+        }
         
         match self.llm.generate(request).await {
             Ok(response) => {
@@ -96,55 +114,8 @@ impl<'a> PlanWorkflow<'a> {
         }
     }
 
-    fn create_planning_prompt(&self, sprint_content: &str) -> String {
-        format!(
-            r#"You are a senior software architect and project manager. Analyze the following sprint document and create a detailed implementation plan.
-
-SPRINT CONTENT:
-{}
-
-Please create a structured task plan with the following requirements:
-
-1. OVERVIEW: Summarize the main goals and deliverables
-2. TASKS: Break down into specific, actionable tasks with:
-   - Clear titles and descriptions
-   - Target files to modify/create
-   - Time estimates in minutes
-   - Task types (Implementation, Refactor, Testing, Documentation, Configuration)
-   - Validation criteria for completion
-
-3. DEPENDENCIES: Identify task dependencies and execution order
-4. PRIORITY: Assess overall priority (Low/Medium/High/Critical)
-5. DURATION: Estimate total implementation time
-
-Focus on:
-- Rust best practices and idiomatic code
-- Comprehensive error handling
-- Production-ready implementations (no TODOs or placeholders)
-- Testability and maintainability
-- Following existing project patterns
-
-Respond with a JSON structure matching this format:
-{{
-  "overview": "Brief description of what will be accomplished",
-  "tasks": [
-    {{
-      "task_id": "unique-id",
-      "title": "Task title",
-      "description": "Detailed description",
-      "file_targets": ["path/to/file1.rs", "path/to/file2.rs"],
-      "estimated_minutes": 60,
-      "task_type": "Implementation|Refactor|Testing|Documentation|Configuration",
-      "validation_criteria": ["How to verify completion"]
-    }}
-  ],
-  "estimated_duration_minutes": 240,
-  "priority": "High",
-  "dependencies": ["task-id-1", "task-id-2"]
-}}
-"#,
-            sprint_content
-        )
+    fn create_planning_prompt(&self, prompts: &PromptLibrary, sprint_content: &str) -> Result<RenderedPrompt> {
+        prompts.render("plan", &[("sprint_content", sprint_content.to_string())])
     }
 
     async fn parse_llm_response_to_plan(&self, response: &str, sprint_file: &PathBuf) -> Result<TaskPlan> {
@@ -306,10 +277,11 @@ mod tests {
         let workflow = PlanWorkflow::new(&llm, &base_path);
         
         let content = "Test sprint content";
-        let prompt = workflow.create_planning_prompt(content);
+        let prompt = workflow.create_planning_prompt(&PromptLibrary::builtin(), content).unwrap();
         
-        assert!(prompt.contains("Test sprint content"));
-        assert!(prompt.contains("JSON structure"));
-        assert!(prompt.contains("Rust best practices"));
+        assert!(prompt.text.contains("Test sprint content"));
+        assert!(prompt.text.contains("JSON structure"));
+        assert!(prompt.text.contains("Rust best practices"));
+        assert_eq!(prompt.template.id, "plan");
     }
 }
\ No newline at end of file
@@ -0,0 +1,329 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::UNIX_EPOCH;
+use tokio::fs;
+
+use crate::llm::embeddings::cosine_similarity;
+use crate::llm::{Attachment, LlmRouter};
+
+/// Index location relative to the repository root
+pub const INDEX_FILE: &str = "index/repo_index.json";
+
+/// Lines per chunk, with `CHUNK_OVERLAP` lines shared between neighbours
+const CHUNK_LINES: usize = 60;
+const CHUNK_OVERLAP: usize = 10;
+/// Larger files are generated or vendored more often than not
+const MAX_FILE_BYTES: u64 = 256 * 1024;
+const EMBED_BATCH: usize = 32;
+
+const INDEXED_EXTENSIONS: &[&str] = &[
+    "rs", "toml", "md", "yaml", "yml", "json", "py", "js", "ts", "tsx", "go", "java", "c", "h", "cpp", "sh",
+];
+/// Skipped when the tree is walked without git; includes DeskAgent's own artifact directories
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules", "index", "runs", "routing", "logs"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexFile {
+    /// Embedder the vectors came from; a different one invalidates them all
+    model: String,
+    files: BTreeMap<String, IndexedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedFile {
+    modified_ms: u64,
+    chunks: Vec<IndexedChunk>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedChunk {
+    start_line: usize,
+    end_line: usize,
+    vector: Vec<f32>,
+}
+
+/// A chunk of a file ranked against a query. Lines are 1-based and inclusive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub score: f32,
+}
+
+/// What a refresh changed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RefreshSummary {
+    pub embedded: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+/// Chunked embeddings of the repository's source files, stored at `INDEX_FILE`
+pub struct RepoIndex {
+    root: PathBuf,
+    data: IndexFile,
+}
+
+impl RepoIndex {
+    /// Load the index for `root`; a missing or unreadable index starts empty
+    pub async fn load(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let data = match fs::read_to_string(root.join(INDEX_FILE)).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("Rebuilding unreadable repository index: {}", e);
+                IndexFile { model: String::new(), files: BTreeMap::new() }
+            }),
+            Err(_) => IndexFile { model: String::new(), files: BTreeMap::new() },
+        };
+        Self { root, data }
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.data.files.len()
+    }
+
+    /// Re-embed files whose mtime changed and drop files that no longer exist
+    pub async fn refresh(&mut self, llm: &LlmRouter) -> Result<RefreshSummary> {
+        let model = llm.embedding_model();
+        if self.data.model != model {
+            self.data = IndexFile { model, files: BTreeMap::new() };
+        }
+
+        let mut summary = RefreshSummary::default();
+        let files = list_files(&self.root).await?;
+
+        let before = self.data.files.len();
+        self.data.files.retain(|path, _| files.contains(path));
+        summary.removed = before - self.data.files.len();
+
+        for path in files {
+            let full_path = self.root.join(&path);
+            let Ok(metadata) = fs::metadata(&full_path).await else { continue };
+            if metadata.len() > MAX_FILE_BYTES {
+                self.data.files.remove(&path);
+                continue;
+            }
+            let modified_ms = metadata.modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_millis() as u64);
+
+            if self.data.files.get(&path).is_some_and(|f| f.modified_ms == modified_ms) {
+                summary.unchanged += 1;
+                continue;
+            }
+
+            // Binary files are not worth indexing
+            let Ok(content) = fs::read_to_string(&full_path).await else {
+                self.data.files.remove(&path);
+                continue;
+            };
+
+            let chunks = chunk_lines(&content);
+            let mut indexed = Vec::with_capacity(chunks.len());
+            for batch in chunks.chunks(EMBED_BATCH) {
+                let texts: Vec<String> = batch.iter()
+                    .map(|(_, _, text)| format!("{}\n{}", path, text))
+                    .collect();
+                let vectors = llm.embed(&texts).await?;
+                if vectors.len() != batch.len() {
+                    return Err(anyhow!("Embedder returned {} vectors for {} chunks", vectors.len(), batch.len()));
+                }
+                indexed.extend(batch.iter().zip(vectors).map(|((start_line, end_line, _), vector)| IndexedChunk {
+                    start_line: *start_line,
+                    end_line: *end_line,
+                    vector,
+                }));
+            }
+
+            self.data.files.insert(path, IndexedFile { modified_ms, chunks: indexed });
+            summary.embedded += 1;
+        }
+
+        Ok(summary)
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        let path = self.root.join(INDEX_FILE);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(&path, serde_json::to_string(&self.data)?).await?;
+        Ok(())
+    }
+
+    /// The `limit` chunks most similar to `query`, at most one per file
+    pub async fn search(&self, llm: &LlmRouter, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        let query_vector = llm.embed(&[query.to_string()]).await?
+            .pop()
+            .ok_or_else(|| anyhow!("Embedder returned no vector for the query"))?;
+
+        let mut hits: Vec<SearchHit> = self.data.files.iter()
+            .filter_map(|(path, file)| {
+                file.chunks.iter()
+                    .map(|chunk| SearchHit {
+                        path: path.clone(),
+                        start_line: chunk.start_line,
+                        end_line: chunk.end_line,
+                        score: cosine_similarity(&query_vector, &chunk.vector),
+                    })
+                    .max_by(|a, b| a.score.total_cmp(&b.score))
+            })
+            .filter(|hit| hit.score > 0.0)
+            .collect();
+
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(limit);
+        Ok(hits)
+    }
+}
+
+/// Refresh the index under `root` and attach the chunks most relevant to `query`.
+/// Retrieval only adds context, so failures are logged and yield no attachments.
+pub async fn relevant_context(llm: &LlmRouter, root: &Path, query: &str, limit: usize) -> Vec<Attachment> {
+    let mut index = RepoIndex::load(root).await;
+    let result = async {
+        let summary = index.refresh(llm).await?;
+        if summary.embedded > 0 || summary.removed > 0 {
+            log::info!("Repository index refreshed: {:?}", summary);
+            index.save().await?;
+        }
+        index.search(llm, query, limit).await
+    }.await;
+
+    let hits = match result {
+        Ok(hits) => hits,
+        Err(e) => {
+            log::warn!("Repository retrieval skipped: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut attachments = Vec::with_capacity(hits.len());
+    for hit in hits {
+        match Attachment::file_excerpt(root.join(&hit.path), Some(hit.start_line..=hit.end_line)).await {
+            Ok(Attachment::File { start_line, end_line, content, .. }) => {
+                attachments.push(Attachment::File { path: hit.path, start_line, end_line, content });
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("Not attaching {}: {}", hit.path, e),
+        }
+    }
+    attachments
+}
+
+/// Split into overlapping line windows of (first line, last line, text)
+fn chunk_lines(content: &str) -> Vec<(usize, usize, String)> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let end = (start + CHUNK_LINES).min(lines.len());
+        chunks.push((start + 1, end, lines[start..end].join("\n")));
+        if end == lines.len() {
+            break;
+        }
+        start = end - CHUNK_OVERLAP;
+    }
+    chunks
+}
+
+/// Tracked files from git, or a directory walk when `root` is not a git checkout
+async fn list_files(root: &Path) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(["ls-files", "--cached", "--others", "--exclude-standard"])
+        .current_dir(root)
+        .output();
+
+    let mut files = match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.to_string())
+            .collect(),
+        _ => walk(root).await?,
+    };
+    files.retain(|path| is_indexed(Path::new(path)) && !path.starts_with("index/"));
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+async fn walk(root: &Path) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+            if entry.file_type().await?.is_dir() {
+                if !SKIPPED_DIRS.contains(&name.as_str()) {
+                    pending.push(path);
+                }
+            } else if let Ok(relative) = path.strip_prefix(root) {
+                files.push(relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn is_indexed(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| INDEXED_EXTENSIONS.contains(&ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::LlmConfig;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_chunks_overlap() {
+        let content = (1..=130).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n");
+        let spans: Vec<_> = chunk_lines(&content).into_iter().map(|(start, end, _)| (start, end)).collect();
+        assert_eq!(spans, vec![(1, 60), (51, 110), (101, 130)]);
+        assert!(chunk_lines("").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_is_incremental_and_search_ranks_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let llm = LlmRouter::new(LlmConfig::default(), root.join("routing").to_str().unwrap()).await.unwrap();
+
+        fs::create_dir_all(root.join("src")).await.unwrap();
+        fs::write(root.join("src/health.rs"), "pub struct CircuitBreaker {}\nfn record_failure() {}\n").await.unwrap();
+        fs::write(root.join("src/tui.rs"), "fn render_keybindings_help() {}\n").await.unwrap();
+        fs::write(root.join("logo.png"), [0u8, 1, 2]).await.unwrap();
+
+        let mut index = RepoIndex::load(root).await;
+        let first = index.refresh(&llm).await.unwrap();
+        assert_eq!(first, RefreshSummary { embedded: 2, ..Default::default() });
+        index.save().await.unwrap();
+
+        // Unchanged mtimes are skipped; deleted files are dropped
+        fs::remove_file(root.join("src/tui.rs")).await.unwrap();
+        let mut reloaded = RepoIndex::load(root).await;
+        let second = reloaded.refresh(&llm).await.unwrap();
+        assert_eq!(second, RefreshSummary { unchanged: 1, removed: 1, ..Default::default() });
+
+        fs::write(root.join("src/tui.rs"), "fn render_keybindings_help() {}\n").await.unwrap();
+        reloaded.refresh(&llm).await.unwrap();
+        let hits = reloaded.search(&llm, "circuit breaker failure handling", 1).await.unwrap();
+        assert_eq!(hits[0].path, "src/health.rs");
+        assert_eq!((hits[0].start_line, hits[0].end_line), (1, 2));
+
+        let attachments = relevant_context(&llm, root, "keybindings help", 1).await;
+        assert!(attachments[0].as_text().unwrap().starts_with("File: src/tui.rs (lines 1-1)"));
+    }
+}
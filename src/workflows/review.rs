@@ -2,10 +2,27 @@ use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
-use std::process::Command;
 use tokio::fs;
 
-use crate::llm::LlmRouter;
+use crate::desktop::output::{CargoMessages, CompilerDiagnostic, DiffNumstat, GitStatus, LibtestReport};
+use crate::desktop::terminal::CommandResult;
+use crate::desktop::{shell, PolicyContext, TerminalController};
+use crate::llm::attachment::image_media_type;
+use crate::llm::{Attachment, LlmRouter, PromptLibrary, RenderedPrompt, TaskType};
+use uuid::Uuid;
+use super::repo_index;
+
+/// Directories whose images are attached to the review request when enabled: GUI test screenshots and plan diagrams
+const REVIEW_IMAGE_DIRS: &[&str] = &["screenshots", "plans"];
+
+/// Upper bound on images attached to one review request
+const MAX_REVIEW_IMAGES: usize = 4;
+
+/// Repository chunks retrieved for the changed hunks
+const RETRIEVED_CHUNKS: usize = 4;
+
+/// Cap on diff text used as the retrieval query
+const MAX_QUERY_CHARS: usize = 8_000;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReviewResult {
@@ -136,14 +153,52 @@ pub enum ApprovalStatus {
     Rejected,
 }
 
+impl ReviewResult {
+    /// Short description of the review kept in the run's conversation history
+    pub fn conversation_digest(&self) -> String {
+        let recommendations = self.recommendations.iter()
+            .map(|r| format!("- [{:?}] {}: {}", r.priority, r.title, r.description))
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!(
+            "Review {} ({:?}, score {:.1})\nSummary: {}\nSecurity: {}\nPerformance: {}\nArchitecture: {}\nRecommendations:\n{}",
+            self.review_id,
+            self.approval_status,
+            self.overall_score,
+            self.llm_analysis.code_review_summary,
+            self.llm_analysis.security_assessment,
+            self.llm_analysis.performance_analysis,
+            self.llm_analysis.architectural_feedback,
+            recommendations,
+        )
+    }
+}
+
 pub struct ReviewWorkflow<'a> {
     llm: &'a LlmRouter,
+    terminal: &'a TerminalController,
     base_path: &'a PathBuf,
+    /// Tools run in the repository as REVIEW commands, audited under the task id once one is set
+    context: PolicyContext,
+    /// Attach screenshots and diagrams to the review request; off by default since images cost tokens
+    attach_images: bool,
 }
 
 impl<'a> ReviewWorkflow<'a> {
-    pub fn new(llm: &'a LlmRouter, base_path: &'a PathBuf) -> Self {
-        Self { llm, base_path }
+    pub fn new(llm: &'a LlmRouter, terminal: &'a TerminalController, base_path: &'a PathBuf) -> Self {
+        let context = PolicyContext::new(base_path.clone()).with_task(TaskType::Review);
+        Self { llm, terminal, base_path, context, attach_images: false }
+    }
+
+    /// Attribute the commands this review runs to `task_id` in the audit log
+    pub fn with_task_id(mut self, task_id: Uuid) -> Self {
+        self.context = self.context.with_task_id(task_id);
+        self
+    }
+
+    pub fn with_image_attachments(mut self, enabled: bool) -> Self {
+        self.attach_images = enabled;
+        self
     }
 
     pub async fn execute(&self) -> Result<serde_json::Value> {
@@ -190,50 +245,31 @@ impl<'a> ReviewWorkflow<'a> {
 
     async fn analyze_git_changes(&self) -> Result<GitAnalysis> {
         // Get git diff information
-        let diff_output = Command::new("git")
-            .args(&["diff", "--stat"])
-            .current_dir(self.base_path)
-            .output();
-
-        let diff_summary = match diff_output {
-            Ok(output) => String::from_utf8_lossy(&output.stdout).to_string(),
+        let diff_summary = match self.run_tool("git", &["diff", "--stat"]).await {
+            Ok(output) => output.stdout,
             Err(_) => "No git repository or changes".to_string(),
         };
 
-        // Get list of changed files
-        let changed_files_output = Command::new("git")
-            .args(&["diff", "--name-only"])
-            .current_dir(self.base_path)
-            .output();
-
-        let files_changed = match changed_files_output {
-            Ok(output) => String::from_utf8_lossy(&output.stdout)
-                .lines()
-                .map(|s| s.to_string())
-                .collect(),
-            Err(_) => Vec::new(),
-        };
-
-        // Get line count changes
-        let (lines_added, lines_removed) = self.parse_diff_stats(&diff_summary);
+        // Changed files and line counts
+        let numstat = self.run_tool("git", &["diff", "--numstat"]).await
+            .and_then(|result| result.parsed::<DiffNumstat>())
+            .unwrap_or_default();
 
         // Check branch status
-        let branch_output = Command::new("git")
-            .args(&["status", "--porcelain", "-b"])
-            .current_dir(self.base_path)
-            .output();
-
-        let branch_status = match branch_output {
-            Ok(output) => String::from_utf8_lossy(&output.stdout).to_string(),
-            Err(_) => "Unknown branch status".to_string(),
+        let status = self.run_tool("git", &["status", "--porcelain=v2", "--branch"]).await
+            .and_then(|result| result.parsed::<GitStatus>());
+
+        let (branch_status, commits_ahead) = match status {
+            Ok(status) => (status.summary(), status.branch.map(|branch| branch.ahead).unwrap_or(0)),
+            Err(_) => ("Unknown branch status".to_string(), 0),
         };
 
         Ok(GitAnalysis {
             diff_summary,
-            files_changed,
-            lines_added,
-            lines_removed,
-            commits_ahead: 0, // Could be calculated with git rev-list
+            files_changed: numstat.paths(),
+            lines_added: numstat.lines_added(),
+            lines_removed: numstat.lines_deleted(),
+            commits_ahead,
             branch_status,
         })
     }
@@ -241,22 +277,23 @@ impl<'a> ReviewWorkflow<'a> {
     async fn analyze_code_quality(&self) -> Result<CodeQualityAnalysis> {
         // Run cargo check for compilation status
         let compile_start = std::time::Instant::now();
-        let check_output = Command::new("cargo")
-            .args(&["check", "--message-format=json"])
-            .current_dir(self.base_path)
-            .output();
+        let check_output = self.run_tool("cargo", &["check", "--message-format=json"]).await;
 
         let compile_time_ms = compile_start.elapsed().as_millis() as u64;
 
         let compilation_status = match check_output {
             Ok(output) => {
-                let success = output.status.success();
-                let stderr = String::from_utf8_lossy(&output.stderr);
+                let success = output.exit_code == 0;
+                let messages = output.parsed::<CargoMessages>().unwrap_or_default();
+                let mut errors: Vec<String> = messages.errors().map(describe_diagnostic).collect();
+                if !success && errors.is_empty() {
+                    errors.push(output.stderr.trim().to_string());
+                }
                 
                 CompilationStatus {
                     success,
-                    errors: if success { Vec::new() } else { vec![stderr.to_string()] },
-                    warnings: Vec::new(),
+                    errors,
+                    warnings: messages.warnings().map(describe_diagnostic).collect(),
                     compile_time_ms,
                 }
             }
@@ -296,31 +333,12 @@ impl<'a> ReviewWorkflow<'a> {
 
     async fn run_tests(&self) -> Result<TestResults> {
         let test_start = std::time::Instant::now();
-        let test_output = Command::new("cargo")
-            .args(&["test", "--", "--report-time"])
-            .current_dir(self.base_path)
-            .output();
+        let test_output = self.run_tool("cargo", &["test"]).await;
 
         let test_time_ms = test_start.elapsed().as_millis() as u64;
 
         match test_output {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                let combined_output = format!("{}\n{}", stdout, stderr);
-                
-                let (total, passed, failed, ignored) = self.parse_test_results(&combined_output);
-                let failing_tests = self.extract_failing_tests(&combined_output);
-
-                Ok(TestResults {
-                    total_tests: total,
-                    passed,
-                    failed,
-                    ignored,
-                    test_time_ms,
-                    failing_tests,
-                })
-            }
+            Ok(output) => Ok(test_results(&output, test_time_ms)),
             Err(e) => Ok(TestResults {
                 total_tests: 0,
                 passed: 0,
@@ -342,12 +360,68 @@ impl<'a> ReviewWorkflow<'a> {
         })
     }
 
+    /// Changed hunks without context lines, falling back to the changed file names
+    async fn diff_hunks(&self, git: &GitAnalysis) -> String {
+        let hunks = self.run_tool("git", &["diff", "-U0"]).await
+            .map(|output| output.stdout)
+            .unwrap_or_default();
+
+        let mut query: String = hunks.lines()
+            .filter(|line| line.starts_with("@@") || line.starts_with('+') || line.starts_with('-'))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if query.trim().is_empty() {
+            query = git.files_changed.join("\n");
+        }
+        if query.len() > MAX_QUERY_CHARS {
+            let end = (0..=MAX_QUERY_CHARS).rev().find(|&i| query.is_char_boundary(i)).unwrap_or(0);
+            query.truncate(end);
+        }
+        query
+    }
+
+    /// Screenshots and diagrams the reviewer should see, newest files first
+    async fn collect_image_attachments(&self) -> Vec<Attachment> {
+        let mut images = Vec::new();
+        for dir in REVIEW_IMAGE_DIRS {
+            let Ok(mut entries) = fs::read_dir(self.base_path.join(dir)).await else { continue };
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let path = entry.path();
+                if image_media_type(&path).is_none() {
+                    continue;
+                }
+                let modified = entry.metadata().await.and_then(|m| m.modified()).ok();
+                images.push((modified, path));
+            }
+        }
+        images.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+
+        let mut attachments = Vec::new();
+        for (_, path) in images {
+            if attachments.len() == MAX_REVIEW_IMAGES {
+                break;
+            }
+            match Attachment::image_file(&path).await {
+                Ok(attachment) => attachments.push(attachment),
+                Err(e) => log::warn!("Not attaching {} to review: {}", path.display(), e),
+            }
+        }
+        attachments
+    }
+
     async fn generate_llm_analysis(&self, git: &GitAnalysis, quality: &CodeQualityAnalysis, tests: &TestResults) -> Result<LLMAnalysis> {
-        let analysis_prompt = self.create_analysis_prompt(git, quality, tests);
+        let prompts = PromptLibrary::load(self.base_path.join("prompts")).await?;
+        let analysis_prompt = self.create_analysis_prompt(&prompts, git, quality, tests)?;
         
         // Create LLM request
-        let messages = vec![crate::llm::Message::user(analysis_prompt)];
-        let request = crate::llm::LlmRequest::new(crate::llm::TaskType::Review, messages);
+        let mut request = crate::llm::LlmRequest::from_prompt(crate::llm::TaskType::Review, analysis_prompt);
+        if let Some(message) = request.messages.last_mut() {
+            if self.attach_images {
+                message.attachments.extend(self.collect_image_attachments().await);
+            }
+            let query = self.diff_hunks(git).await;
+            message.attachments.extend(repo_index::relevant_context(self.llm, self.base_path, &query, RETRIEVED_CHUNKS).await);
+        }
         
         match self.llm.generate(request).await {
             Ok(response) => {
@@ -501,94 +575,13 @@ impl<'a> ReviewWorkflow<'a> {
 
     // Helper methods for parsing and formatting
 
-    fn parse_diff_stats(&self, diff_output: &str) -> (u32, u32) {
-        // Parse git diff --stat output to extract line counts
-        let lines: Vec<&str> = diff_output.lines().collect();
-        if let Some(summary_line) = lines.last() {
-            if summary_line.contains("insertion") || summary_line.contains("deletion") {
-                let mut added = 0u32;
-                let mut removed = 0u32;
-                
-                if let Some(plus_pos) = summary_line.find("(+)") {
-                    let prefix = &summary_line[..plus_pos];
-                    if let Some(start) = prefix.rfind(' ') {
-                        if let Ok(num) = summary_line[start + 1..plus_pos].trim().parse::<u32>() {
-                            added = num;
-                        }
-                    }
-                }
-                
-                if let Some(minus_pos) = summary_line.find("(-)") {
-                    let prefix = &summary_line[..minus_pos];
-                    if let Some(start) = prefix.rfind(' ') {
-                        if let Ok(num) = summary_line[start + 1..minus_pos].trim().parse::<u32>() {
-                            removed = num;
-                        }
-                    }
-                }
-                
-                return (added, removed);
-            }
-        }
-        (0, 0)
-    }
-
-    fn parse_test_results(&self, output: &str) -> (u32, u32, u32, u32) {
-        // Parse cargo test output
-        let lines: Vec<&str> = output.lines().collect();
-        
-        for line in lines {
-            if line.contains("test result:") {
-                // Look for pattern like "test result: ok. 15 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out"
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                let mut passed = 0u32;
-                let mut failed = 0u32;
-                let mut ignored = 0u32;
-                
-                for (i, part) in parts.iter().enumerate() {
-                    if *part == "passed;" && i > 0 {
-                        passed = parts[i - 1].parse().unwrap_or(0);
-                    } else if *part == "failed;" && i > 0 {
-                        failed = parts[i - 1].parse().unwrap_or(0);
-                    } else if *part == "ignored;" && i > 0 {
-                        ignored = parts[i - 1].parse().unwrap_or(0);
-                    }
-                }
-                
-                let total = passed + failed + ignored;
-                return (total, passed, failed, ignored);
-            }
-        }
-        
-        (0, 0, 0, 0)
-    }
-
-    fn extract_failing_tests(&self, output: &str) -> Vec<String> {
-        // Extract names of failing tests from cargo test output
-        let mut failing_tests = Vec::new();
-        let lines: Vec<&str> = output.lines().collect();
-        
-        for line in lines {
-            if line.contains("FAILED") && line.contains("test ") {
-                if let Some(test_name) = line.split_whitespace().find(|&s| s.starts_with("test")) {
-                    failing_tests.push(test_name.to_string());
-                }
-            }
-        }
-        
-        failing_tests
-    }
-
     async fn run_clippy_analysis(&self) -> Result<LintResults> {
-        let clippy_output = Command::new("cargo")
-            .args(&["clippy", "--message-format=json"])
-            .current_dir(self.base_path)
-            .output();
+        let clippy_output = self.run_tool("cargo", &["clippy", "--message-format=json"]).await
+            .and_then(|output| output.parsed::<CargoMessages>());
 
         match clippy_output {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let issues = self.parse_clippy_output(&stdout);
+            Ok(messages) => {
+                let issues = lint_issues(&messages);
                 let warnings = issues.iter().filter(|i| i.severity == "warning").count() as u32;
                 let errors = issues.iter().filter(|i| i.severity == "error").count() as u32;
                 
@@ -606,22 +599,17 @@ impl<'a> ReviewWorkflow<'a> {
         }
     }
 
-    fn parse_clippy_output(&self, _output: &str) -> Vec<LintIssue> {
-        // This is a simplified parser - in reality you'd parse the JSON output
-        Vec::new()
+    /// Run a tool in the repository through the terminal controller, which applies its
+    /// sandbox and audits the command for this review. Builds and test suites outlast the
+    /// command timeout, so tools get the controller's tool timeout.
+    async fn run_tool(&self, program: &str, args: &[&str]) -> Result<CommandResult> {
+        let command = std::iter::once(program).chain(args.iter().copied()).map(shell::quote).collect::<Vec<_>>().join(" ");
+        self.terminal.execute_command_in_with_timeout(&command, &self.context, self.terminal.tool_timeout()).await
     }
 
     async fn check_formatting(&self) -> Result<Vec<String>> {
-        let fmt_output = Command::new("cargo")
-            .args(&["fmt", "--check"])
-            .current_dir(self.base_path)
-            .output();
-
-        match fmt_output {
-            Ok(output) if !output.status.success() => {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                Ok(stderr.lines().map(|s| s.to_string()).collect())
-            }
+        match self.run_tool("cargo", &["fmt", "--check"]).await {
+            Ok(output) if output.exit_code != 0 => Ok(output.stderr.lines().map(|s| s.to_string()).collect()),
             _ => Ok(Vec::new()),
         }
     }
@@ -636,45 +624,21 @@ impl<'a> ReviewWorkflow<'a> {
         })
     }
 
-    fn create_analysis_prompt(&self, git: &GitAnalysis, quality: &CodeQualityAnalysis, tests: &TestResults) -> String {
-        format!(
-            r#"Analyze the following code review data and provide structured feedback:
-
-GIT CHANGES:
-- Files changed: {}
-- Lines added: {}, removed: {}
-- Diff summary: {}
-
-CODE QUALITY:
-- Compilation: {}
-- Lint warnings: {}, errors: {}
-- Formatting issues: {}
-
-TEST RESULTS:
-- Total tests: {}, passed: {}, failed: {}
-- Test time: {}ms
-
-Please provide analysis in the following sections:
-SUMMARY: Overall assessment
-SECURITY: Security considerations
-PERFORMANCE: Performance implications  
-ARCHITECTURE: Architectural feedback
-
-Rate maintainability on a scale of 1-10.
-"#,
-            git.files_changed.len(),
-            git.lines_added,
-            git.lines_removed,
-            git.diff_summary.lines().take(3).collect::<Vec<_>>().join(" "),
-            if quality.compilation_status.success { "SUCCESS" } else { "FAILED" },
-            quality.lint_results.warnings,
-            quality.lint_results.errors,
-            quality.formatting_issues.len(),
-            tests.total_tests,
-            tests.passed,
-            tests.failed,
-            tests.test_time_ms
-        )
+    fn create_analysis_prompt(&self, prompts: &PromptLibrary, git: &GitAnalysis, quality: &CodeQualityAnalysis, tests: &TestResults) -> Result<RenderedPrompt> {
+        prompts.render("review", &[
+            ("files_changed", git.files_changed.len().to_string()),
+            ("lines_added", git.lines_added.to_string()),
+            ("lines_removed", git.lines_removed.to_string()),
+            ("diff_summary", git.diff_summary.lines().take(3).collect::<Vec<_>>().join(" ")),
+            ("compilation", if quality.compilation_status.success { "SUCCESS" } else { "FAILED" }.to_string()),
+            ("lint_warnings", quality.lint_results.warnings.to_string()),
+            ("lint_errors", quality.lint_results.errors.to_string()),
+            ("formatting_issues", quality.formatting_issues.len().to_string()),
+            ("total_tests", tests.total_tests.to_string()),
+            ("tests_passed", tests.passed.to_string()),
+            ("tests_failed", tests.failed.to_string()),
+            ("test_time_ms", tests.test_time_ms.to_string()),
+        ])
     }
 
     fn extract_section(&self, response: &str, section_name: &str) -> Option<String> {
@@ -813,9 +777,65 @@ Rate maintainability on a scale of 1-10.
     }
 }
 
+/// `file:line:col: message` for a compiler diagnostic
+fn describe_diagnostic(diagnostic: &CompilerDiagnostic) -> String {
+    match diagnostic.primary_span() {
+        Some(span) => format!("{}:{}:{}: {}", span.file_name, span.line_start, span.column_start, diagnostic.message),
+        None => diagnostic.message.clone(),
+    }
+}
+
+/// Errors and warnings from `cargo clippy --message-format=json`
+fn lint_issues(messages: &CargoMessages) -> Vec<LintIssue> {
+    messages.diagnostics()
+        .filter(|diagnostic| diagnostic.level == "warning" || diagnostic.level.starts_with("error"))
+        .map(|diagnostic| {
+            let span = diagnostic.primary_span();
+            LintIssue {
+                file: span.map(|span| span.file_name.clone()).unwrap_or_default(),
+                line: span.map_or(0, |span| span.line_start),
+                column: span.map_or(0, |span| span.column_start),
+                severity: if diagnostic.level.starts_with("error") { "error".to_string() } else { diagnostic.level.clone() },
+                message: diagnostic.message.clone(),
+                rule: diagnostic.code.as_ref().map(|code| code.code.clone()).unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+/// Test counts from `cargo test`; a failed run whose failures the report does not show
+/// (a build error, a timeout, a crashed test binary) counts as one failure
+fn test_results(output: &CommandResult, test_time_ms: u64) -> TestResults {
+    let report = output.parsed::<LibtestReport>().unwrap_or_default();
+    let (passed, mut failed, ignored) = (report.passed(), report.failed(), report.ignored());
+    let mut failing_tests: Vec<String> = report.failing_tests().into_iter().map(String::from).collect();
+
+    if (output.exit_code != 0 || output.timed_out) && failed == 0 {
+        failed = 1;
+        let last_error = output.stderr.lines().rev().find(|line| !line.trim().is_empty());
+        failing_tests.push(if output.timed_out {
+            format!("cargo test timed out after {}ms", output.duration_ms)
+        } else if let Some(line) = last_error {
+            format!("cargo test exited with code {}: {}", output.exit_code, line.trim())
+        } else {
+            format!("cargo test exited with code {}", output.exit_code)
+        });
+    }
+
+    TestResults {
+        total_tests: passed + failed + ignored,
+        passed,
+        failed,
+        ignored,
+        test_time_ms,
+        failing_tests,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::desktop::CommandAuditLog;
     use tempfile::TempDir;
     use std::sync::Arc;
 
@@ -827,34 +847,165 @@ mod tests {
     #[tokio::test]
     async fn test_review_workflow_creation() {
         let llm = create_test_llm().await;
+        let terminal = TerminalController::new();
         let base_path = PathBuf::from(".");
-        let workflow = ReviewWorkflow::new(&llm, &base_path);
+        let workflow = ReviewWorkflow::new(&llm, &terminal, &base_path);
         
         assert!(std::ptr::eq(workflow.llm, &llm));
         assert_eq!(workflow.base_path, &base_path);
+        assert_eq!(workflow.context.task_type, Some(TaskType::Review));
+    }
+
+    #[tokio::test]
+    async fn test_review_tools_are_audited_for_the_task() {
+        let llm = create_test_llm().await;
+        let temp_dir = TempDir::new().unwrap();
+        let audit = Arc::new(CommandAuditLog::new(temp_dir.path().join("commands.jsonl")));
+        let terminal = TerminalController::new().with_audit_log(audit.clone());
+        let base_path = temp_dir.path().to_path_buf();
+        let task_id = Uuid::new_v4();
+        let workflow = ReviewWorkflow::new(&llm, &terminal, &base_path).with_task_id(task_id);
+
+        let result = workflow.run_tool("git", &["--version"]).await.unwrap();
+        assert_eq!(result.command, "git --version");
+
+        let entries = audit.take_unreported();
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].task_id, entries[0].task_type.clone()), (Some(task_id), Some(TaskType::Review)));
+        assert_eq!(entries[0].cwd, base_path);
+    }
+
+    #[tokio::test]
+    async fn test_review_tools_outlast_the_command_timeout() {
+        let llm = create_test_llm().await;
+        let terminal = TerminalController::with_config("Terminal".to_string(), 100, 1);
+        let base_path = PathBuf::from(".");
+        let workflow = ReviewWorkflow::new(&llm, &terminal, &base_path);
+
+        let result = workflow.run_tool("sleep", &["0.5"]).await.unwrap();
+        assert!(!result.timed_out);
+        assert_eq!(result.exit_code, 0);
+    }
+
+    #[tokio::test]
+    async fn test_review_tool_arguments_are_not_split_or_expanded() {
+        let llm = create_test_llm().await;
+        let terminal = TerminalController::new();
+        let base_path = PathBuf::from(".");
+        let workflow = ReviewWorkflow::new(&llm, &terminal, &base_path);
+
+        let result = workflow.run_tool("printf", &["%s|", "a b", "*", "$HOME"]).await.unwrap();
+        assert_eq!(result.stdout, "a b|*|$HOME|");
     }
 
     #[test]
-    fn test_parse_diff_stats() {
-        let rt = tokio::runtime::Runtime::new().unwrap();
-        let llm = rt.block_on(async { create_test_llm().await });
+    fn test_lint_issues_from_clippy_json() {
+        let stdout = concat!(
+            r#"{"reason":"compiler-message","package_id":"app 0.1.0","target":{"kind":["lib"],"name":"app"},"message":{"message":"unneeded `return` statement","level":"warning","code":{"code":"clippy::needless_return","explanation":null},"spans":[{"file_name":"src/lib.rs","line_start":4,"line_end":4,"column_start":5,"column_end":14,"is_primary":true,"label":null}],"children":[],"rendered":null}}"#, "\n",
+            r#"{"reason":"compiler-message","package_id":"app 0.1.0","target":{"kind":["lib"],"name":"app"},"message":{"message":"mismatched types","level":"error","code":{"code":"E0308","explanation":null},"spans":[{"file_name":"src/lib.rs","line_start":9,"line_end":9,"column_start":12,"column_end":13,"is_primary":true,"label":null}],"children":[],"rendered":null}}"#, "\n",
+            r#"{"reason":"build-finished","success":false}"#, "\n",
+        );
+        let result = CommandResult {
+            command: "cargo clippy --message-format=json".to_string(),
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+            exit_code: 101,
+            duration_ms: 1,
+            timed_out: false,
+        };
+        let messages = result.parsed::<CargoMessages>().unwrap();
+        let issues = lint_issues(&messages);
+        
+        assert_eq!(issues.len(), 2);
+        assert_eq!((issues[0].file.as_str(), issues[0].line, issues[0].column), ("src/lib.rs", 4, 5));
+        assert_eq!(issues[0].rule, "clippy::needless_return");
+        assert_eq!(issues[1].severity, "error");
+        assert_eq!(describe_diagnostic(messages.errors().next().unwrap()), "src/lib.rs:9:12: mismatched types");
+    }
+
+    #[test]
+    fn test_failed_test_run_without_summary_is_a_failure() {
+        let result = CommandResult {
+            command: "cargo test".to_string(),
+            stdout: String::new(),
+            stderr: "   Compiling deskagent v0.1.0\nerror: could not compile `deskagent`\n".to_string(),
+            exit_code: 101,
+            duration_ms: 1200,
+            timed_out: false,
+        };
+        let results = test_results(&result, 1200);
+        assert_eq!((results.total_tests, results.passed, results.failed), (1, 0, 1));
+        assert_eq!(results.failing_tests, vec!["cargo test exited with code 101: error: could not compile `deskagent`"]);
+
+        let passing = CommandResult {
+            stdout: "test a ... ok\ntest b ... FAILED\ntest result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out\n".to_string(),
+            ..result
+        };
+        let results = test_results(&passing, 1200);
+        assert_eq!((results.total_tests, results.passed, results.failed), (2, 1, 1));
+        assert_eq!(results.failing_tests, vec!["b"]);
+    }
+
+    #[tokio::test]
+    async fn test_analysis_prompt_uses_review_template() {
+        let llm = create_test_llm().await;
+        let terminal = TerminalController::new();
         let base_path = PathBuf::from(".");
-        let workflow = ReviewWorkflow::new(&llm, &base_path);
+        let workflow = ReviewWorkflow::new(&llm, &terminal, &base_path);
         
-        let diff_output = " src/main.rs | 10 +++++-----\n src/lib.rs  |  5 +++++\n 2 files changed, 10 insertions(+), 5 deletions(-)";
-        let (added, removed) = workflow.parse_diff_stats(diff_output);
+        let git = GitAnalysis {
+            diff_summary: "src/main.rs | 2 +-".to_string(),
+            files_changed: vec!["src/main.rs".to_string()],
+            lines_added: 1,
+            lines_removed: 1,
+            commits_ahead: 0,
+            branch_status: "clean".to_string(),
+        };
+        let quality = CodeQualityAnalysis {
+            lint_results: LintResults { warnings: 2, errors: 0, issues: Vec::new() },
+            compilation_status: CompilationStatus { success: true, errors: Vec::new(), warnings: Vec::new(), compile_time_ms: 10 },
+            formatting_issues: Vec::new(),
+            complexity_metrics: ComplexityMetrics { cyclomatic_complexity: 1.0, cognitive_complexity: 1.0, lines_of_code: 10, function_count: 1 },
+        };
+        let tests = TestResults { total_tests: 5, passed: 4, failed: 1, ignored: 0, test_time_ms: 30, failing_tests: Vec::new() };
         
-        // Note: This is a simplified test - actual parsing would be more robust
-        assert_eq!(added, 0);
-        assert_eq!(removed, 0);
+        let prompt = workflow.create_analysis_prompt(&PromptLibrary::builtin(), &git, &quality, &tests).unwrap();
+        assert_eq!(prompt.template.id, "review");
+        assert!(prompt.text.contains("Lint warnings: 2, errors: 0"));
+        assert!(prompt.text.contains("passed: 4, failed: 1"));
+    }
+
+    #[tokio::test]
+    async fn test_review_attaches_screenshots_and_diagrams() {
+        let llm = create_test_llm().await;
+        let terminal = TerminalController::new();
+        let temp_dir = TempDir::new().unwrap();
+        let base_path = temp_dir.path().to_path_buf();
+        fs::create_dir_all(base_path.join("screenshots")).await.unwrap();
+        fs::create_dir_all(base_path.join("plans")).await.unwrap();
+        fs::write(base_path.join("screenshots/main_window.png"), b"\x89PNG").await.unwrap();
+        fs::write(base_path.join("plans/architecture.jpg"), b"\xff\xd8").await.unwrap();
+        fs::write(base_path.join("plans/sprint-01.plan.json"), "{}").await.unwrap();
+        for i in 0..MAX_REVIEW_IMAGES {
+            fs::write(base_path.join(format!("screenshots/extra_{}.webp", i)), b"RIFF").await.unwrap();
+        }
+
+        let workflow = ReviewWorkflow::new(&llm, &terminal, &base_path);
+        let attachments = workflow.collect_image_attachments().await;
+        assert_eq!(attachments.len(), MAX_REVIEW_IMAGES);
+        assert!(attachments.iter().all(|a| matches!(a, Attachment::Image { .. })));
+
+        let empty = temp_dir.path().join("missing");
+        assert!(ReviewWorkflow::new(&llm, &terminal, &empty).collect_image_attachments().await.is_empty());
     }
 
     #[test]
     fn test_calculate_overall_score() {
         let rt = tokio::runtime::Runtime::new().unwrap();
         let llm = rt.block_on(async { create_test_llm().await });
+        let terminal = TerminalController::new();
         let base_path = PathBuf::from(".");
-        let workflow = ReviewWorkflow::new(&llm, &base_path);
+        let workflow = ReviewWorkflow::new(&llm, &terminal, &base_path);
         
         let quality = CodeQualityAnalysis {
             lint_results: LintResults { warnings: 2, errors: 0, issues: Vec::new() },
@@ -873,8 +1024,9 @@ mod tests {
     fn test_extract_section() {
         let rt = tokio::runtime::Runtime::new().unwrap();
         let llm = rt.block_on(async { create_test_llm().await });
+        let terminal = TerminalController::new();
         let base_path = PathBuf::from(".");
-        let workflow = ReviewWorkflow::new(&llm, &base_path);
+        let workflow = ReviewWorkflow::new(&llm, &terminal, &base_path);
         
         let response = "SUMMARY: This is the summary section\n\nSECURITY: Security looks good\n\nEnd of response";
         
@@ -91,10 +91,14 @@ async fn test_terminal_session_management() -> Result<()> {
     let session = terminal.create_session().await?;
     
     assert!(!session.get_session_id().is_empty());
-    assert!(!session.is_active().await);
+    assert!(session.is_active().await);
+    
+    let result = session.run("cd / && pwd").await?;
+    assert_eq!(result.stdout, "/\n");
     
     // Test session termination
     session.terminate().await?;
+    assert!(!session.is_active().await);
     
     println!("✅ Terminal session management test passed");
     Ok(())
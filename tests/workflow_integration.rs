@@ -117,7 +117,8 @@ async fn test_review_workflow_execution() {
     let llm_config = deskagent::llm::LlmConfig::default();
     let llm = LlmRouter::new(llm_config, "logs").await.unwrap();
     
-    let review_workflow = ReviewWorkflow::new(&llm, &base_path);
+    let terminal = TerminalController::new();
+    let review_workflow = ReviewWorkflow::new(&llm, &terminal, &base_path);
     let result = review_workflow.execute().await.unwrap();
     
     // Verify review result structure
@@ -240,6 +241,49 @@ async fn test_workflow_error_handling() {
     assert!(workflow_result.error_message.is_some());
 }
 
+/// Test that PLAN results are kept in the run's conversation for FOLLOWUP questions
+#[tokio::test]
+async fn test_conversation_persisted_with_run() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = OrchestratorConfig {
+        max_concurrent_tasks: 5,
+        task_timeout_ms: 30000,
+        log_directory: temp_dir.path().join("logs").to_string_lossy().to_string(),
+    };
+    
+    let orchestrator = Orchestrator::new(config).await.unwrap();
+    let cursor = CursorController::new();
+    let terminal = TerminalController::new();
+    let llm_config = deskagent::llm::LlmConfig::default();
+    let llm = LlmRouter::new(llm_config, temp_dir.path().join("llm-logs").to_str().unwrap()).await.unwrap();
+    let base_path = temp_dir.path().to_path_buf();
+    let mut manager = WorkflowManager::new(orchestrator, cursor, terminal, llm, base_path.clone());
+    
+    let sprint_file = base_path.join("sprint.md");
+    fs::write(&sprint_file, "# Sprint\n\n- [ ] Add caching").await.unwrap();
+    let plan_result = manager.execute_plan_workflow(sprint_file).await.unwrap();
+    assert_eq!(plan_result.status, WorkflowStatus::Completed);
+    
+    let conversation_file = plan_result.artifacts.iter()
+        .find(|p| p.ends_with("conversation.json"))
+        .expect("conversation stored with the run")
+        .clone();
+    assert!(conversation_file.exists());
+    assert_eq!(manager.get_conversation().turns.len(), 2);
+    
+    // No provider is configured, so the question fails but the history is kept
+    let followup = manager.execute_followup_workflow("Which task is riskiest?".to_string()).await.unwrap();
+    assert_eq!(followup.workflow_type, WorkflowType::Followup);
+    assert_eq!(followup.status, WorkflowStatus::Failed);
+    
+    let conversation_id = manager.get_conversation().id;
+    let run_dir = conversation_file.parent().unwrap();
+    let mut resumed = WorkflowManager::new_for_testing().await.unwrap();
+    resumed.resume_conversation(run_dir).await;
+    assert_eq!(resumed.get_conversation().id, conversation_id);
+    assert!(resumed.get_conversation().turns[1].message.content.contains("Tasks:"));
+}
+
 /// Test workflow result serialization and deserialization
 #[tokio::test]
 async fn test_workflow_result_serialization() {
@@ -409,7 +453,8 @@ edition = "2021"
     let llm_config = deskagent::llm::LlmConfig::default();
     let llm = LlmRouter::new(llm_config, "logs").await.unwrap();
     
-    let review_workflow = ReviewWorkflow::new(&llm, &base_path);
+    let terminal = TerminalController::new();
+    let review_workflow = ReviewWorkflow::new(&llm, &terminal, &base_path);
     let result = review_workflow.execute().await.unwrap();
     
     // Verify comprehensive analysis